@@ -0,0 +1,249 @@
+use crate::color::Color;
+
+// Levels of the blur pyramid: half resolution down to 1/32
+const MAX_LEVELS: usize = 5;
+
+// Separable 5-tap Gaussian (binomial) weights
+const BLUR_WEIGHTS: [f32; 5] = [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
+
+struct Level {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    // Output of the horizontal blur, input of the vertical one
+    scratch: Vec<Color>,
+}
+
+impl Level {
+    fn new(width: usize, height: usize) -> Self {
+        Level {
+            width,
+            height,
+            pixels: vec![Color::black(); width * height],
+            scratch: vec![Color::black(); width * height],
+        }
+    }
+
+    fn at(&self, x: isize, y: isize) -> Color {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    fn blur(&mut self) {
+        let (width, height) = (self.width, self.height);
+        // Texel `i` of the kernel centered on `center`, clamped to the edge
+        let tap = |center: usize, i: usize, limit: usize| (center + i).saturating_sub(2).min(limit - 1);
+
+        for (row, output) in self.pixels.chunks_exact(width).zip(self.scratch.chunks_exact_mut(width)) {
+            for (x, pixel) in output.iter_mut().enumerate() {
+                *pixel = BLUR_WEIGHTS
+                    .iter()
+                    .enumerate()
+                    .fold(Color::black(), |sum, (i, &weight)| sum + row[tap(x, i, width)] * weight);
+            }
+        }
+
+        // The vertical pass adds whole rows at a time
+        for (y, output) in self.pixels.chunks_exact_mut(width).enumerate() {
+            output.fill(Color::black());
+            for (i, &weight) in BLUR_WEIGHTS.iter().enumerate() {
+                let source = tap(y, i, height) * width;
+                for (pixel, &color) in output.iter_mut().zip(&self.scratch[source..source + width]) {
+                    *pixel = *pixel + color * weight;
+                }
+            }
+        }
+    }
+}
+
+// Glow around pixels brighter than white: the bright part of the image is downsampled into a
+// pyramid, each level is blurred, and the levels are upsampled and summed back so the glow
+// has both a tight core and a wide halo. Buffers are kept between frames.
+pub struct Bloom {
+    // Luminance above which a pixel starts to glow (1.0 = white)
+    pub threshold: f32,
+    // Width of the soft transition around the threshold
+    pub knee: f32,
+    // How much of the blurred light is added back to the image
+    pub intensity: f32,
+    size: (usize, usize),
+    levels: Vec<Level>,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.6,
+            size: (0, 0),
+            levels: Vec::new(),
+        }
+    }
+}
+
+impl Bloom {
+    // Adds the glow to `image`, a linear HDR image of `width` x `height` pixels
+    pub fn apply(&mut self, image: &mut [Color], width: usize, height: usize) {
+        if self.size != (width, height) {
+            self.allocate(width, height);
+        }
+        if self.levels.is_empty() {
+            return;
+        }
+
+        self.bright_pass(image, width);
+
+        for level in 1..self.levels.len() {
+            let (finer, coarser) = self.levels.split_at_mut(level);
+            downsample(&finer[level - 1], &mut coarser[0]);
+        }
+        // The half-resolution level is only box-filtered: blurring it would cost as much as all
+        // the others together and barely widen the glow
+        for level in self.levels.iter_mut().skip(1) {
+            level.blur();
+        }
+
+        // Each level gets the (already accumulated) coarser one added on top of it
+        for level in (1..self.levels.len()).rev() {
+            let (finer, coarser) = self.levels.split_at_mut(level);
+            let (source, target) = (&coarser[0], &mut finer[level - 1]);
+            upsample_add(&source.pixels, source.width, source.height, &mut target.pixels, target.width, target.height, 1.0);
+        }
+
+        let glow = &self.levels[0];
+        let weight = self.intensity / self.levels.len() as f32;
+        upsample_add(&glow.pixels, glow.width, glow.height, image, width, height, weight);
+    }
+
+    fn allocate(&mut self, width: usize, height: usize) {
+        self.size = (width, height);
+        self.levels.clear();
+        let (mut level_width, mut level_height) = (width / 2, height / 2);
+        while self.levels.len() < MAX_LEVELS && level_width >= 2 && level_height >= 2 {
+            self.levels.push(Level::new(level_width, level_height));
+            level_width /= 2;
+            level_height /= 2;
+        }
+    }
+
+    // Keeps only the light above the threshold, with a quadratic knee so the glow fades in
+    // smoothly, and downsamples it 2x2 into the first level
+    fn bright_pass(&mut self, image: &[Color], width: usize) {
+        let (threshold, knee) = (self.threshold, self.knee.max(1e-4));
+        let bright = |color: Color| {
+            let luminance = color.luminance();
+            if luminance <= threshold - knee {
+                return Color::black();
+            }
+            let soft = (luminance - threshold + knee).clamp(0.0, 2.0 * knee);
+            let contribution = (soft * soft / (4.0 * knee)).max(luminance - threshold);
+            if luminance > 0.0 { color * (contribution / luminance) } else { Color::black() }
+        };
+
+        let level = &mut self.levels[0];
+        for y in 0..level.height {
+            for x in 0..level.width {
+                let (sx, sy) = (2 * x, 2 * y);
+                let sum = bright(image[sy * width + sx])
+                    + bright(image[sy * width + sx + 1])
+                    + bright(image[(sy + 1) * width + sx])
+                    + bright(image[(sy + 1) * width + sx + 1]);
+                level.pixels[y * level.width + x] = sum * 0.25;
+            }
+        }
+    }
+}
+
+fn downsample(source: &Level, target: &mut Level) {
+    for y in 0..target.height {
+        for x in 0..target.width {
+            let (sx, sy) = (2 * x as isize, 2 * y as isize);
+            let sum = source.at(sx, sy) + source.at(sx + 1, sy) + source.at(sx, sy + 1) + source.at(sx + 1, sy + 1);
+            target.pixels[y * target.width + x] = sum * 0.25;
+        }
+    }
+}
+
+// Bilinear taps for resizing `source` texels to `target`: the two texels (clamped to the
+// edge) and the weight of the second one, for each target texel
+fn resize_taps(source: usize, target: usize) -> Vec<(usize, usize, f32)> {
+    let scale = source as f32 / target as f32;
+    (0..target)
+        .map(|i| {
+            let position = (i as f32 + 0.5) * scale - 0.5;
+            let first = position.floor();
+            let clamp = |texel: f32| (texel.max(0.0) as usize).min(source - 1);
+            (clamp(first), clamp(first + 1.0), position - first)
+        })
+        .collect()
+}
+
+// Adds `source` stretched over `target` with bilinear filtering, scaled by `weight`. Each
+// source row is stretched horizontally once and then blended vertically.
+fn upsample_add(
+    source: &[Color],
+    source_width: usize,
+    source_height: usize,
+    target: &mut [Color],
+    target_width: usize,
+    target_height: usize,
+    weight: f32,
+) {
+    let columns = resize_taps(source_width, target_width);
+    let rows = resize_taps(source_height, target_height);
+
+    let stretch = |row: usize, output: &mut Vec<Color>| {
+        let row = &source[row * source_width..(row + 1) * source_width];
+        output.clear();
+        output.extend(columns.iter().map(|&(x0, x1, tx)| row[x0] * (weight * (1.0 - tx)) + row[x1] * (weight * tx)));
+    };
+    let (mut upper, mut lower) = (Vec::with_capacity(target_width), Vec::with_capacity(target_width));
+    let mut cached = None;
+
+    for (target_row, &(y0, y1, ty)) in target.chunks_exact_mut(target_width).zip(&rows) {
+        if cached != Some((y0, y1)) {
+            if cached.map(|(_, previous)| previous) == Some(y0) {
+                std::mem::swap(&mut upper, &mut lower);
+            } else {
+                stretch(y0, &mut upper);
+            }
+            stretch(y1, &mut lower);
+            cached = Some((y0, y1));
+        }
+        for (pixel, (top, bottom)) in target_row.iter_mut().zip(upper.iter().zip(&lower)) {
+            *pixel = *pixel + *top * (1.0 - ty) + *bottom * ty;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_spreads_only_bright_light() {
+        let (width, height) = (64, 64);
+        let center = 32 * width + 32;
+        let mut bloom = Bloom::default();
+
+        // Below the knee nothing glows and the image is left as it was
+        let mut dim = vec![Color::from_f32(0.4, 0.4, 0.4); width * height];
+        bloom.apply(&mut dim, width, height);
+        assert!(dim.iter().all(|&color| color == Color::from_f32(0.4, 0.4, 0.4)));
+
+        // A pixel brighter than white lights up its neighbourhood, fading with distance
+        let mut image = vec![Color::black(); width * height];
+        image[center] = Color::from_f32(50.0, 40.0, 30.0);
+        bloom.apply(&mut image, width, height);
+        let near = image[center + 2].luminance();
+        let far = image[center + 12].luminance();
+        let corner = image[0].luminance();
+        assert!(near > far && far > corner, "near {} far {} corner {}", near, far, corner);
+
+        // The glow keeps the hue of the light
+        let glow = image[center + 4];
+        assert!(glow.r() > glow.g() && glow.g() > glow.b());
+    }
+}
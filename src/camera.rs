@@ -0,0 +1,382 @@
+use nalgebra_glm::{self as glm, Mat4, Quat, Vec3};
+
+const DEFAULT_FOV: f32 = 45.0;
+const MIN_FOV: f32 = 5.0;
+const MAX_FOV: f32 = 120.0;
+// Fraction of the linear and angular velocity kept from one frame to the next
+const DAMPING: f32 = 0.85;
+
+// Perspective camera shared by every draw call: it turns world positions into screen pixels
+// through its view, projection and viewport matrices. It looks down its local -z axis with +y
+// up, and the viewport flips y so that up in the camera is up on the screen.
+//
+// It also flies freely: the orientation is a quaternion, so pitch, yaw and roll always turn
+// around the camera's own axes without gimbal lock, and moving happens relative to where it
+// is looking, with velocities that build up and die out smoothly.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub position: Vec3,
+    // Camera-to-world rotation
+    pub orientation: Quat,
+    // World units per frame
+    pub velocity: Vec3,
+    // Radians per frame of pitch, yaw and roll (around the camera's x, y and z axes)
+    pub angular_velocity: Vec3,
+    // Direction and length, in skybox radii, that the stars are stretched along into hyperspace
+    // streaks while warping (zero otherwise)
+    pub streak: Vec3,
+    // Vertical field of view, in degrees
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    width: usize,
+    height: usize,
+}
+
+impl Camera {
+    pub fn new(position: Vec3, orientation: Quat, width: usize, height: usize) -> Self {
+        Camera {
+            position,
+            orientation,
+            velocity: Vec3::zeros(),
+            angular_velocity: Vec3::zeros(),
+            streak: Vec3::zeros(),
+            fov: DEFAULT_FOV,
+            near: 10.0,
+            far: 100_000.0,
+            width,
+            height,
+        }
+    }
+
+    // Unit vector the camera is looking along, in world space
+    pub fn forward(&self) -> Vec3 {
+        forward(&self.orientation)
+    }
+
+    pub fn pose(&self) -> Pose {
+        Pose { position: self.position, orientation: self.orientation, fov: self.fov }
+    }
+
+    pub fn set_pose(&mut self, pose: Pose) {
+        self.position = pose.position;
+        self.orientation = pose.orientation;
+        self.fov = pose.fov;
+    }
+
+    // Turns the camera by `angles` radians of pitch, yaw and roll around its own axes
+    pub fn rotate(&mut self, angles: Vec3) {
+        let turn = glm::quat_angle_axis(angles.y, &Vec3::y())
+            * glm::quat_angle_axis(angles.x, &Vec3::x())
+            * glm::quat_angle_axis(angles.z, &Vec3::z());
+        self.orientation = glm::quat_normalize(&(self.orientation * turn));
+    }
+
+    // Advances one frame: `thrust` (along the camera's right, up and backward axes) and `turn`
+    // (pitch, yaw and roll) are added to the velocities, which are then damped so the camera
+    // eases to a stop when the controls are released
+    pub fn fly(&mut self, thrust: Vec3, turn: Vec3) {
+        self.velocity = (self.velocity + glm::quat_rotate_vec3(&self.orientation, &thrust)) * DAMPING;
+        self.angular_velocity = (self.angular_velocity + turn) * DAMPING;
+        self.position += self.velocity;
+        self.rotate(self.angular_velocity);
+    }
+
+    // Advances one frame circling `target` at `distance`: `turn` builds up the angular velocity
+    // like in `fly`, and after turning the camera steps back so the target stays centered
+    pub fn orbit(&mut self, target: Vec3, distance: f32, turn: Vec3) {
+        self.velocity = Vec3::zeros();
+        self.angular_velocity = (self.angular_velocity + turn) * DAMPING;
+        self.rotate(self.angular_velocity);
+        self.frame(target, distance);
+    }
+
+    pub fn stop(&mut self) {
+        self.velocity = Vec3::zeros();
+        self.angular_velocity = Vec3::zeros();
+        self.streak = Vec3::zeros();
+    }
+
+    // Moves the camera back along its view direction until it is `distance` away from `target`
+    pub fn frame(&mut self, target: Vec3, distance: f32) {
+        self.position = target - self.forward() * distance;
+    }
+
+    // Narrows (factor < 1) or widens (factor > 1) the field of view
+    pub fn zoom(&mut self, factor: f32) {
+        self.fov = (self.fov * factor).clamp(MIN_FOV, MAX_FOV);
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        glm::quat_to_mat4(&glm::quat_conjugate(&self.orientation)) * glm::translation(&-self.position)
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        glm::perspective(self.width as f32 / self.height as f32, self.fov.to_radians(), self.near, self.far)
+    }
+
+    pub fn viewport_matrix(&self) -> Mat4 {
+        let (half_width, half_height) = (self.width as f32 / 2.0, self.height as f32 / 2.0);
+        glm::translation(&Vec3::new(half_width, half_height, 0.0)) * glm::scaling(&Vec3::new(half_width, -half_height, 1.0))
+    }
+
+    // Ray from the camera through the point (x, y) of the screen, in pixels
+    pub fn ray(&self, x: f32, y: f32) -> Ray {
+        let half_height = (self.fov.to_radians() / 2.0).tan();
+        let half_width = half_height * self.width as f32 / self.height as f32;
+        let direction = Vec3::new(
+            (2.0 * x / self.width as f32 - 1.0) * half_width,
+            (1.0 - 2.0 * y / self.height as f32) * half_height,
+            -1.0,
+        );
+        Ray { origin: self.position, direction: glm::quat_rotate_vec3(&self.orientation, &direction).normalize() }
+    }
+
+    // Size in world units of a pixel at `distance` from the camera
+    pub fn pixel_size(&self, distance: f32) -> f32 {
+        2.0 * distance * (self.fov.to_radians() / 2.0).tan() / self.height as f32
+    }
+}
+
+fn forward(orientation: &Quat) -> Vec3 {
+    glm::quat_rotate_vec3(orientation, &Vec3::new(0.0, 0.0, -1.0))
+}
+
+// Half-line from `origin` along the unit `direction`, in world space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    // Distance along the ray to where it enters a sphere (or leaves it, from inside)
+    pub fn sphere(&self, center: &Vec3, radius: f32) -> Option<f32> {
+        let offset = self.origin - center;
+        let along = offset.dot(&self.direction);
+        let discriminant = along * along - (offset.norm_squared() - radius * radius);
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        [-along - root, -along + root].into_iter().find(|&distance| distance >= 0.0)
+    }
+
+    // Distance along the ray to where it crosses a flat annulus around `center`, perpendicular
+    // to the unit `normal`
+    pub fn ring(&self, center: &Vec3, normal: &Vec3, inner_radius: f32, outer_radius: f32) -> Option<f32> {
+        let facing = self.direction.dot(normal);
+        if facing.abs() < 1e-6 {
+            return None;
+        }
+        let distance = (center - self.origin).dot(normal) / facing;
+        let crossing = (self.origin + self.direction * distance - center).norm();
+        (distance >= 0.0 && (inner_radius..=outer_radius).contains(&crossing)).then_some(distance)
+    }
+}
+
+// Where the camera is, where it looks and how wide it sees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub fov: f32,
+}
+
+impl Pose {
+    // Looking with `orientation` and the default field of view at `target` from `distance` away
+    pub fn framing(target: Vec3, distance: f32, orientation: Quat) -> Self {
+        Pose { position: target - forward(&orientation) * distance, orientation, fov: DEFAULT_FOV }
+    }
+}
+
+// Animated flight from a starting pose to a destination over a number of frames, easing in and
+// out. The destination may move while in transit (a planet keeps orbiting), so it's passed to
+// every step instead of being fixed at the start.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Warp {
+    from: Pose,
+    frame: u32,
+    duration: u32,
+}
+
+impl Warp {
+    pub fn new(from: Pose, duration: u32) -> Self {
+        Warp { from, frame: 0, duration }
+    }
+
+    // Advances one frame and returns the pose on the way to `to`
+    pub fn step(&mut self, to: &Pose) -> Pose {
+        self.frame = (self.frame + 1).min(self.duration);
+        let t = smoothstep(self.progress());
+        Pose {
+            position: glm::lerp(&self.from.position, &to.position, t),
+            orientation: slerp(&self.from.orientation, &to.orientation, t),
+            fov: self.from.fov + (to.fov - self.from.fov) * t,
+        }
+    }
+
+    // Fraction of the duration already flown, from 0.0 to 1.0
+    pub fn progress(&self) -> f32 {
+        if self.duration == 0 { 1.0 } else { self.frame as f32 / self.duration as f32 }
+    }
+
+    // Speed relative to the top speed, reached halfway: the slope of the easing curve
+    pub fn speed(&self) -> f32 {
+        let t = self.progress();
+        4.0 * t * (1.0 - t)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.duration
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Spherical interpolation along the shortest arc; nearly equal rotations are blended linearly,
+// where the spherical formula would divide by almost zero
+fn slerp(from: &Quat, to: &Quat, t: f32) -> Quat {
+    let mut cosine = glm::quat_dot(from, to);
+    let to = if cosine < 0.0 {
+        cosine = -cosine;
+        -to
+    } else {
+        *to
+    };
+    if cosine > 0.9995 {
+        return glm::quat_normalize(&glm::quat_lerp(from, &to, t));
+    }
+    let angle = cosine.acos();
+    (from * ((1.0 - t) * angle).sin() + to * (t * angle).sin()) / angle.sin()
+}
+
+// True for a perspective projection; an orthographic one (like the identity) keeps w = 1
+pub fn is_perspective(projection_matrix: &Mat4) -> bool {
+    projection_matrix[(3, 2)] != 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_projects_farther_bodies_smaller() {
+        let orientation = glm::quat_angle_axis(0.9, &Vec3::new(0.3, -0.8, 0.1).normalize());
+        let mut camera = Camera::new(Vec3::zeros(), orientation, 200, 100);
+        let target = Vec3::new(100.0, 50.0, -20.0);
+        let to_screen = |camera: &Camera, point: Vec3| {
+            let clip = camera.projection_matrix() * camera.view_matrix() * point.push(1.0);
+            (camera.viewport_matrix() * (clip / clip.w)).xyz()
+        };
+        // Screen radius of a sphere of radius 10 around the target, measured along the camera's up
+        let up = glm::quat_rotate_vec3(&camera.orientation, &Vec3::y());
+        let screen_radius = |camera: &Camera| {
+            let (center, top) = (to_screen(camera, target), to_screen(camera, target + up * 10.0));
+            assert!((center.x - 100.0).abs() < 1e-2 && (center.y - 50.0).abs() < 1e-2, "framed target off center: {:?}", center);
+            // Up in the camera is up on the screen
+            assert!(top.y < center.y);
+            center.y - top.y
+        };
+
+        camera.frame(target, 200.0);
+        let near = screen_radius(&camera);
+        assert!((near - 10.0 / camera.pixel_size(200.0)).abs() < 0.05, "radius {} vs pixel size", near);
+        camera.frame(target, 800.0);
+        let far = screen_radius(&camera);
+        assert!((near / far - 4.0).abs() < 0.01, "near {} far {}", near, far);
+
+        // Zooming in narrows the field of view and enlarges the body
+        camera.zoom(0.5);
+        assert!(screen_radius(&camera) > far * 1.9);
+    }
+
+    #[test]
+    fn free_fly_camera_turns_and_moves_relative_to_its_view() {
+        let mut camera = Camera::new(Vec3::zeros(), glm::quat_identity(), 200, 100);
+        let close = |a: Vec3, b: Vec3| (a - b).norm() < 1e-4;
+
+        // Pitching up 90° and then yawing left turns around the camera's own up axis, which now
+        // points along world z, instead of locking up like fixed-order Euler angles
+        let quarter = std::f32::consts::FRAC_PI_2;
+        camera.rotate(Vec3::new(quarter, 0.0, 0.0));
+        assert!(close(camera.forward(), Vec3::y()), "forward {:?}", camera.forward());
+        camera.rotate(Vec3::new(0.0, quarter, 0.0));
+        assert!(close(camera.forward(), -Vec3::x()), "forward {:?}", camera.forward());
+        // Rolling keeps the view direction
+        camera.rotate(Vec3::new(0.0, 0.0, 1.0));
+        assert!(close(camera.forward(), -Vec3::x()), "forward {:?}", camera.forward());
+
+        // Forward thrust moves along the view direction and speeds up while held
+        let mut last_step = 0.0;
+        for _ in 0..10 {
+            let before = camera.position;
+            camera.fly(Vec3::new(0.0, 0.0, -1.0), Vec3::zeros());
+            let step = camera.position - before;
+            assert!(close(step.normalize(), -Vec3::x()) && step.norm() > last_step);
+            last_step = step.norm();
+        }
+
+        // Once released it eases to a stop instead of halting at once
+        camera.fly(Vec3::zeros(), Vec3::zeros());
+        assert!(camera.velocity.norm() > 0.5 * last_step);
+        for _ in 0..100 {
+            camera.fly(Vec3::zeros(), Vec3::zeros());
+        }
+        assert!(camera.velocity.norm() < 1e-5);
+
+        // Turning builds up the same way, and many small turns keep the orientation a unit quaternion
+        for _ in 0..1000 {
+            camera.fly(Vec3::zeros(), Vec3::new(0.01, -0.02, 0.03));
+        }
+        assert!((glm::quat_length(&camera.orientation) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn warp_eases_into_a_moving_destination() {
+        let from = Pose { position: Vec3::new(0.0, 0.0, 1000.0), orientation: glm::quat_identity(), fov: 60.0 };
+        // Half a turn away: the interpolation must still pick a path instead of failing
+        let orientation = glm::quat_angle_axis(std::f32::consts::PI, &Vec3::x());
+        let mut warp = Warp::new(from, 30);
+
+        let mut previous = from;
+        let mut steps = Vec::new();
+        while !warp.is_finished() {
+            // The destination keeps moving while in transit
+            let target = Vec3::new(500.0 + 10.0 * steps.len() as f32, 200.0, 0.0);
+            let to = Pose::framing(target, 100.0, orientation);
+            let pose = warp.step(&to);
+            assert!((glm::quat_length(&pose.orientation) - 1.0).abs() < 1e-4);
+            steps.push(((pose.position - previous.position).norm(), warp.speed()));
+            previous = pose;
+            if warp.is_finished() {
+                assert_eq!(pose.position, to.position);
+                assert!(glm::quat_dot(&pose.orientation, &to.orientation).abs() > 0.9999);
+                assert_eq!(pose.fov, to.fov);
+            }
+        }
+        assert_eq!(steps.len(), 30);
+
+        // Eased in and out: slow at both ends and fastest halfway
+        let fastest = steps.iter().map(|step| step.0).fold(0.0, f32::max);
+        assert!(steps[0].0 < fastest * 0.2 && steps[29].0 < fastest * 0.2);
+        assert!(steps[14].0 > fastest * 0.9 && steps[14].1 > 0.99);
+        assert_eq!(warp.speed(), 0.0);
+    }
+
+    #[test]
+    fn ray_passes_through_the_projected_point() {
+        let orientation = glm::quat_angle_axis(2.1, &Vec3::x());
+        let mut camera = Camera::new(Vec3::zeros(), orientation, 200, 150);
+        camera.frame(Vec3::zeros(), 5000.0);
+        let point = Vec3::new(300.0, -200.0, 50.0);
+        let clip = camera.projection_matrix() * camera.view_matrix() * point.push(1.0);
+        let screen = camera.viewport_matrix() * (clip / clip.w);
+
+        let ray = camera.ray(screen.x, screen.y);
+        assert_eq!(ray.origin, camera.position);
+        assert!((ray.direction - (point - camera.position).normalize()).norm() < 1e-4);
+    }
+}
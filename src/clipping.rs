@@ -0,0 +1,130 @@
+use nalgebra_glm::{Mat4, Vec4};
+use crate::shaders::project_to_screen;
+use crate::vertex::Vertex;
+
+// Smallest w accepted before the perspective division; anything closer is cut away
+const W_EPSILON: f32 = 1e-5;
+
+#[derive(Clone, Copy)]
+enum Plane {
+    W,
+    Left,
+    Right,
+    Bottom,
+    Top,
+    Near,
+    Far,
+}
+
+const FRUSTUM_PLANES: [Plane; 7] = [
+    Plane::W,
+    Plane::Left,
+    Plane::Right,
+    Plane::Bottom,
+    Plane::Top,
+    Plane::Near,
+    Plane::Far,
+];
+
+impl Plane {
+    // Signed distance in homogeneous clip space; the vertex is inside when it is >= 0
+    fn distance(self, p: &Vec4) -> f32 {
+        match self {
+            Plane::W => p.w - W_EPSILON,
+            Plane::Left => p.x + p.w,
+            Plane::Right => p.w - p.x,
+            Plane::Bottom => p.y + p.w,
+            Plane::Top => p.w - p.y,
+            Plane::Near => p.z + p.w,
+            Plane::Far => p.w - p.z,
+        }
+    }
+}
+
+// Polygon buffers reused by every clipped triangle, so clipping doesn't allocate once they have
+// grown to the largest polygon (3 vertices plus one per plane)
+#[derive(Default)]
+pub struct ClipScratch {
+    polygon: Vec<Vertex>,
+    next: Vec<Vertex>,
+}
+
+// Clips the triangle `indices` (into `vertices`) against the view volume in homogeneous
+// space (Sutherland-Hodgman) and fans the resulting polygon back into triangles. The w > 0
+// plane is always applied so nothing behind the camera reaches the perspective division;
+// the six frustum planes only when `frustum` is set, since they are meaningless without a
+// real projection matrix. Vertices created by clipping are appended to `vertices` and the
+// surviving triangles are appended to `output` as index triples.
+pub fn clip_triangle(
+    indices: [usize; 3],
+    vertices: &mut Vec<Vertex>,
+    frustum: bool,
+    viewport_matrix: &Mat4,
+    scratch: &mut ClipScratch,
+    output: &mut Vec<[usize; 3]>,
+) {
+    let planes: &[Plane] = if frustum { &FRUSTUM_PLANES } else { &FRUSTUM_PLANES[..1] };
+
+    // Common case: fully inside, keep the triangle untouched
+    let inside = planes.iter().all(|plane| {
+        indices.iter().all(|&index| plane.distance(&vertices[index].clip_position) >= 0.0)
+    });
+    if inside {
+        output.push(indices);
+        return;
+    }
+
+    let ClipScratch { polygon, next } = scratch;
+    polygon.clear();
+    polygon.extend(indices.iter().map(|&index| vertices[index].clone()));
+
+    for &plane in planes {
+        next.clear();
+        for i in 0..polygon.len() {
+            let current = &polygon[i];
+            let following = &polygon[(i + 1) % polygon.len()];
+            let d_current = plane.distance(&current.clip_position);
+            let d_following = plane.distance(&following.clip_position);
+
+            if d_current >= 0.0 {
+                next.push(current.clone());
+            }
+            if (d_current >= 0.0) != (d_following >= 0.0) {
+                let t = d_current / (d_current - d_following);
+                next.push(interpolate_vertex(current, following, t));
+            }
+        }
+        std::mem::swap(polygon, next);
+        if polygon.len() < 3 {
+            return;
+        }
+    }
+
+    let first = vertices.len();
+    vertices.extend(polygon.drain(..).map(|mut vertex| {
+        vertex.transformed_position = project_to_screen(&vertex.clip_position, viewport_matrix);
+        vertex
+    }));
+
+    for i in first + 1..vertices.len() - 1 {
+        output.push([first, i, i + 1]);
+    }
+}
+
+fn interpolate_vertex(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    let transformed_normal = a.transformed_normal.lerp(&b.transformed_normal, t);
+    Vertex {
+        position: a.position.lerp(&b.position, t),
+        normal: a.normal.lerp(&b.normal, t),
+        tex_coords: a.tex_coords.lerp(&b.tex_coords, t),
+        color: a.color.lerp(&b.color, t),
+        transformed_position: a.transformed_position.lerp(&b.transformed_position, t),
+        transformed_normal: if transformed_normal.norm() > 0.0 {
+            transformed_normal.normalize()
+        } else {
+            transformed_normal
+        },
+        world_position: a.world_position.lerp(&b.world_position, t),
+        clip_position: a.clip_position.lerp(&b.clip_position, t),
+    }
+}
@@ -2,52 +2,81 @@ use std::fmt;
 use std::ops::Mul;
 use std::ops::Add;
 
-#[derive(Debug, Clone, Copy)]
+// Color lineal en punto flotante: 1.0 equivale a 255 pero los canales no se saturan,
+// así una luz puede ser más brillante que el blanco hasta el mapeo de tonos
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
-    r: u8,
-    g: u8,
-    b: u8,
+    r: f32,
+    g: f32,
+    b: f32,
+    // Opacidad: 1.0 es opaco, 0.0 transparente
+    a: f32,
 }
 
+#[allow(dead_code)]
 impl Color {
     pub const fn new(r: u8, g: u8, b: u8) -> Self {
-        Color { r, g, b }
+        Color::rgba(r, g, b, 255)
+    }
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        const SCALE: f32 = 1.0 / 255.0;
+        Color { r: r as f32 * SCALE, g: g as f32 * SCALE, b: b as f32 * SCALE, a: a as f32 * SCALE }
+    }
+    // Canales en unidades lineales (1.0 = 255), sin límite superior
+    pub const fn from_f32(r: f32, g: f32, b: f32) -> Self {
+        Color { r, g, b, a: 1.0 }
     }
     pub const fn from_hex(hex: u32) -> Self {
         let r = ((hex >> 16) & 0xFF) as u8;
         let g = ((hex >> 8) & 0xFF) as u8;
         let b = (hex & 0xFF) as u8;
-        Color { r, g, b }
+        Color::new(r, g, b)
     }
     pub const fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
+        Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }
     }
-    pub const fn to_hex(&self) -> u32 {
-        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    // Satura cada canal a [0, 1]; para mostrar colores HDR hay que aplicar antes un mapeo de tonos
+    pub fn to_hex(self) -> u32 {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u32;
+        (channel(self.r) << 16) | (channel(self.g) << 8) | channel(self.b)
     }
-    pub fn r(&self) -> u8 {
+    pub fn r(&self) -> f32 {
         self.r
     }
 
-    pub fn g(&self) -> u8 {
+    pub fn g(&self) -> f32 {
         self.g
     }
 
-    pub fn b(&self) -> u8 {
+    pub fn b(&self) -> f32 {
         self.b
     }
 
+    pub fn a(&self) -> f32 {
+        self.a
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Color { a: a.clamp(0.0, 1.0), ..self }
+    }
+
+    // Luminancia relativa (Rec. 709)
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     pub fn lerp(&self, other: &Color, t: f32) -> Self {
         let t = t.clamp(0.0, 1.0);
         Color {
-            r: (self.r as f32 + (other.r as f32 - self.r as f32) * t).round() as u8,
-            g: (self.g as f32 + (other.g as f32 - self.g as f32) * t).round() as u8,
-            b: (self.b as f32 + (other.b as f32 - self.b as f32) * t).round() as u8,
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
         }
     }
 
     pub fn is_black(&self) -> bool {
-        self.r == 0 && self.g == 0 && self.b == 0
+        self.r == 0.0 && self.g == 0.0 && self.b == 0.0
     }
     
     pub fn blend_normal(&self, blend: &Color) -> Color {
@@ -55,34 +84,26 @@ impl Color {
     }
     
     pub fn blend_multiply(&self, blend: &Color) -> Color {
-        Color::new(
-            ((self.r as f32 * blend.r as f32) / 255.0) as u8,
-            ((self.g as f32 * blend.g as f32) / 255.0) as u8,
-            ((self.b as f32 * blend.b as f32) / 255.0) as u8
-        )
+        Color::from_f32(self.r * blend.r, self.g * blend.g, self.b * blend.b)
     }
     
     pub fn blend_add(&self, blend: &Color) -> Color {
-        Color::new(
-            (self.r as u16 + blend.r as u16).min(255) as u8,
-            (self.g as u16 + blend.g as u16).min(255) as u8,
-            (self.b as u16 + blend.b as u16).min(255) as u8
-        )
+        Color::from_f32(self.r + blend.r, self.g + blend.g, self.b + blend.b)
     }
     
     pub fn blend_subtract(&self, blend: &Color) -> Color {
-        Color::new(
-            self.r.saturating_sub(blend.r),
-            self.g.saturating_sub(blend.g),
-            self.b.saturating_sub(blend.b)
+        Color::from_f32(
+            (self.r - blend.r).max(0.0),
+            (self.g - blend.g).max(0.0),
+            (self.b - blend.b).max(0.0)
         )
     }
     
     pub fn blend_screen(&self, blend: &Color) -> Color {
-        Color::new(
-            255 - ((255 - self.r as u16) * (255 - blend.r as u16) / 255) as u8,
-            255 - ((255 - self.g as u16) * (255 - blend.g as u16) / 255) as u8,
-            255 - ((255 - self.b as u16) * (255 - blend.b as u16) / 255) as u8
+        Color::from_f32(
+            1.0 - (1.0 - self.r) * (1.0 - blend.r),
+            1.0 - (1.0 - self.g) * (1.0 - blend.g),
+            1.0 - (1.0 - self.b) * (1.0 - blend.b)
         )
     }    
 }
@@ -92,9 +113,10 @@ impl Add for Color {
 
     fn add(self, other: Color) -> Color {
         Color {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
+            a: self.a.max(other.a),
         }
     }
 }
@@ -105,15 +127,16 @@ impl Mul<f32> for Color {
 
     fn mul(self, scalar: f32) -> Color {
         Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+            r: (self.r * scalar).max(0.0),
+            g: (self.g * scalar).max(0.0),
+            b: (self.b * scalar).max(0.0),
+            a: self.a,
         }
     }
 }
 
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+        write!(f, "Color(r: {:.3}, g: {:.3}, b: {:.3}, a: {:.3})", self.r, self.g, self.b, self.a)
     }
-}
\ No newline at end of file
+}
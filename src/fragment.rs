@@ -1,6 +1,8 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 use crate::Uniforms;
+use crate::camera::is_perspective;
+use crate::lighting::{Light, SurfaceMaterial};
 use fastnoise_lite::FastNoiseLite;
 
 
@@ -10,20 +12,119 @@ pub struct Fragment {
     pub intensity: f32,
     pub vertex_position: Vec3,
     pub normal: Vec3,
+    pub tex_coords: Vec2,
+    pub color: Color,
+    // Posición en el mundo, para calcular la dirección de la luz
+    pub world_position: Vec3,
+    // Derivadas en pantalla de tex_coords (un píxel a la derecha y uno hacia abajo)
+    pub uv_dx: Vec2,
+    pub uv_dy: Vec2,
 }
 
 impl Fragment {
-    pub fn new(position: Vec2, depth: f32, intensity: f32, vertex_position: Vec3, normal: Vec3) -> Self {
+    pub fn new(
+        position: Vec2,
+        depth: f32,
+        intensity: f32,
+        vertex_position: Vec3,
+        normal: Vec3,
+        tex_coords: Vec2,
+        color: Color,
+    ) -> Self {
         Fragment {
             position,
             depth,
             intensity,
             vertex_position,
-            normal
+            normal,
+            tex_coords,
+            color,
+            world_position: vertex_position,
+            uv_dx: Vec2::zeros(),
+            uv_dy: Vec2::zeros(),
         }
     }
 }
 
+// Radiancia de la superficie del Sol respecto a un blanco difuso (1.0)
+const SOLAR_RADIANCE: f32 = 4.0;
+
+// Materiales de los cuerpos iluminados por el Sol: ambiente, brillo especular, metalicidad y
+// rugosidad (para el modelo PBR) y emisión
+const VOLCANIC_MATERIAL: SurfaceMaterial = SurfaceMaterial::matte(Color::new(12, 6, 6));
+const CRYSTAL_MATERIAL: SurfaceMaterial = SurfaceMaterial {
+    ambient: Color::new(14, 12, 30),
+    specular: Color::new(230, 240, 255),
+    shininess: 48.0,
+    metallic: 0.0,
+    roughness: 0.25,
+    emissive: Color::black(),
+};
+const VORTEX_MATERIAL: SurfaceMaterial = SurfaceMaterial {
+    ambient: Color::black(),
+    specular: Color::new(40, 40, 40),
+    shininess: 12.0,
+    metallic: 0.0,
+    roughness: 0.6,
+    emissive: Color::black(),
+};
+const RINGED_MATERIAL: SurfaceMaterial = SurfaceMaterial::matte(Color::new(40, 20, 12));
+const ROCK_MATERIAL: SurfaceMaterial = SurfaceMaterial::matte(Color::black());
+const EARTH_MATERIAL: SurfaceMaterial = SurfaceMaterial {
+    ambient: Color::black(),
+    specular: Color::new(60, 60, 60),
+    shininess: 40.0,
+    metallic: 0.0,
+    roughness: 0.45,
+    emissive: Color::black(),
+};
+const GAS_GIANT_MATERIAL: SurfaceMaterial = SurfaceMaterial {
+    ambient: Color::black(),
+    specular: Color::new(25, 25, 25),
+    shininess: 8.0,
+    metallic: 0.0,
+    roughness: 0.8,
+    emissive: Color::black(),
+};
+const JET_MATERIAL: SurfaceMaterial = SurfaceMaterial {
+    ambient: Color::new(50, 50, 80),
+    specular: Color::new(204, 204, 204),
+    shininess: 16.0,
+    metallic: 0.9,
+    roughness: 0.35,
+    emissive: Color::black(),
+};
+
+// Dirección del fragmento hacia el observador en el mundo. Con perspectiva el observador es la
+// posición de la cámara; con una proyección ortográfica la cámara mira a lo largo de +z en el
+// espacio de vista (la vista solo rota y escala de forma uniforme, así que basta con la transpuesta)
+fn view_direction(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    if is_perspective(&uniforms.projection_matrix) {
+        return (uniforms.camera_position - fragment.world_position).normalize();
+    }
+    let direction = uniforms.view_matrix.transpose() * Vec4::new(0.0, 0.0, -1.0, 0.0);
+    direction.xyz().normalize()
+}
+
+// Luz del Sol que llega al fragmento, descontando la que tapan otros cuerpos
+fn visible_light(fragment: &Fragment, uniforms: &Uniforms) -> Light {
+    let visibility = uniforms.shadows.visibility(&uniforms.light, &fragment.world_position);
+    Light { intensity: uniforms.light.intensity * visibility, ..uniforms.light }
+}
+
+// Ilumina el albedo de un fragmento con la luz del Sol según el material y el modelo de
+// iluminación del cuerpo
+fn shade(albedo: Color, material: &SurfaceMaterial, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    material.shade(
+        uniforms.lighting_model,
+        albedo,
+        &visible_light(fragment, uniforms),
+        &fragment.world_position,
+        &fragment.normal,
+        &view_direction(fragment, uniforms),
+    )
+}
+
 // Shaders para planetas
 fn solar_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let x = fragment.vertex_position.x;
@@ -34,12 +135,11 @@ fn solar_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let noise_value = uniforms.noise_open_simplex.get_noise_2d(x * zoom, y * zoom) * 0.3 + 0.7;
     let surface_intensity = (0.9 + noise_value * 0.1) * time_factor; 
 
-    // Color base con variaciones para simular la superficie solar
-    let r = (255.0 * surface_intensity) as u8;
-    let g = (200.0 * surface_intensity) as u8;
-    let b = (50.0 * surface_intensity) as u8;
-
-    let core_color = Color::new(r, g, b) * fragment.intensity;
+    // Color base con variaciones para simular la superficie solar, varias veces más
+    // brillante que el blanco para que el mapeo de tonos conserve el detalle. El Sol emite su
+    // propia luz: solo se oscurece hacia el borde del disco
+    let limb_darkening = view_facing(fragment, uniforms);
+    let core_color = Color::new(255, 200, 50) * (surface_intensity * SOLAR_RADIANCE * limb_darkening);
 
     // Efecto de halo alrededor del Sol
     let distance_to_center = (x.powi(2) + y.powi(2)).sqrt();
@@ -77,12 +177,7 @@ fn volcanic_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
     // Interpolación del patrón rocoso y simulación de grietas
     let color_intermediate = color_roca.lerp(&color_sombra, normalized_noise * 0.8);
-    let base_color = color_intermediate.lerp(&color_mineral, normalized_noise * 0.5);
-
-    // Color final con iluminación ambiental
-    let ambient_intensity = 0.3;
-    let ambient_color = Color::new(40, 20, 20);
-    base_color * fragment.intensity + ambient_color * ambient_intensity
+    color_intermediate.lerp(&color_mineral, normalized_noise * 0.5)
 }
 
 
@@ -101,7 +196,8 @@ fn molten_core_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color
     let lava_color = Color::new(255, (80.0 * lava_pattern) as u8, 0);  
     let rock_color = Color::new((50.0 * (1.0 - noise_value)) as u8, 0, 0);  
 
-    // Mezcla de colores entre el núcleo de lava y las áreas de roca
+    // Mezcla de colores entre el núcleo de lava y las áreas de roca. La lava brilla por sí
+    // misma, así que no depende de la luz del Sol
     if noise_value > 0.3 {
         lava_color.blend_add(&rock_color)
     } else {
@@ -172,9 +268,7 @@ fn vortex_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     );
 
     // Mezclar el color base con el color de brillo para resaltar áreas del vórtice
-    let final_color = base_color.blend_add(&highlight_color) * fragment.intensity;
-
-    final_color
+    base_color.blend_add(&highlight_color)
 }
 
 
@@ -197,14 +291,7 @@ fn ringed_planet(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let g = ((1.0 - combined_pattern) * 150.0) as u8; 
     let b = ((0.5 - combined_pattern) * 50.0) as u8 + 20; 
 
-    let base_color = Color::new(r, g, b);
-
-    // Ajuste de iluminación ambiental para darle suavidad al efecto
-    let ambient_intensity = 0.4;
-    let ambient_color = Color::new(100, 50, 30); 
-
-    // Mezcla del color base y el color ambiental
-    base_color * fragment.intensity + ambient_color * ambient_intensity
+    Color::new(r, g, b)
 }
 
 
@@ -236,7 +323,11 @@ pub fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let color_intermediate = color1.lerp(&color2, val_normalizado);
     let final_color = color_intermediate.lerp(&color3, val_normalizado);
 
-    final_color * 0.9
+    // Las bandas oscuras dejan ver lo que hay detrás del anillo. El anillo no se ilumina con
+    // la dirección del Sol, pero sí se oscurece dentro de la sombra de su planeta
+    let alpha = 0.35 + 0.55 * (1.0 - val_normalizado);
+    let shadow = uniforms.shadows.visibility(&uniforms.light, &fragment.world_position);
+    (final_color * (0.9 * (0.2 + 0.8 * shadow))).with_alpha(alpha)
 }
 
 pub fn rocky_planet(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -257,13 +348,11 @@ pub fn rocky_planet(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let color_intermediate = color_roca.lerp(&color_sombra, normalized_noise * 0.8);
     let base_color = color_intermediate.lerp(&color_mineral, normalized_noise * 0.5);
 
-    let final_color = if is_fracture {
+    if is_fracture {
         Color::new(60, 30, 10)
     } else {
         base_color
-    };
-
-    final_color * fragment.intensity
+    }
 }
 
 fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -277,11 +366,9 @@ fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let color_sombra = Color::new(20, 20, 20); 
     let color_claro = Color::new(150, 150, 150);
 
-    let factor = (ruido + 1.0) as f32 / 2.0;
-    let mut color_final = color_base.lerp(&color_sombra, factor * 0.8); 
-    color_final = color_final.lerp(&color_claro, factor * 0.5);
-
-    color_final * fragment.intensity
+    let factor = (ruido + 1.0) / 2.0;
+    let color_final = color_base.lerp(&color_sombra, factor * 0.8); 
+    color_final.lerp(&color_claro, factor * 0.5)
 }
 
 fn ruido_fractal(noise: &FastNoiseLite, x: f32, y: f32, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
@@ -319,13 +406,11 @@ fn earth_like_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let ocean_color = Color::new(0, 105, 148); 
     let land_color = Color::new(34, 139, 34);  
 
-    let base_color = if is_land {
+    if is_land {
         land_color
     } else {
         ocean_color
-    };
-
-    base_color * fragment.intensity
+    }
 }
 
 fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -339,15 +424,100 @@ fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let base_color = Color::new(70, 130, 180); 
     let cloud_color = Color::new(255, 255, 255); 
 
-    let final_color = base_color.lerp(&cloud_color, normalized_noise * 0.5);
+    base_color.lerp(&cloud_color, normalized_noise * 0.5)
+}
+
+// Tablero de ajedrez a partir de las coordenadas de textura, útil para revisar la interpolación
+fn uv_checker_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    let squares = 8.0;
+    let u = (fragment.tex_coords.x * squares).floor() as i32;
+    let v = (fragment.tex_coords.y * squares).floor() as i32;
+
+    let base_color = if (u + v) % 2 == 0 {
+        Color::new(230, 230, 230)
+    } else {
+        Color::new(200, 40, 40)
+    };
+
+    base_color * (0.3 + fragment.intensity * 0.7)
+}
+
+fn orbit_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    fragment.color
+}
+
+
+// Radio de la corona respecto al del Sol
+pub const CORONA_SCALE: f32 = 1.3;
+
+// Coseno del ángulo entre la normal y la dirección de vista: 1 en el centro del disco, 0 en el borde
+fn view_facing(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+    let normal = fragment.normal / fragment.normal.norm().max(1e-6);
+    normal.dot(&view_direction(fragment, uniforms)).abs()
+}
 
-    final_color * fragment.intensity
+// Seno del mismo ángulo: 0 en el centro del disco, 1 en el borde
+fn view_rim(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+    let facing = view_facing(fragment, uniforms);
+    (1.0 - facing * facing).max(0.0).sqrt()
 }
 
-fn orbit_shader(_fragment: &Fragment, _uniforms: &Uniforms) -> Color {
-    Color::new(255, 255, 255) 
+// Atmósfera translúcida, más densa hacia el borde del planeta (alfa premultiplicado)
+fn atmosphere_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let rim = view_rim(fragment, uniforms);
+    let alpha = rim.powf(3.0) * 0.85;
+    let color = Color::new(90, 160, 255) * (0.3 + 0.7 * fragment.intensity);
+    (color * alpha).with_alpha(alpha)
 }
 
+// Resplandor del Sol: máximo en el borde del disco solar y se desvanece hacia afuera
+// (para mezcla aditiva)
+fn corona_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let distance = view_rim(fragment, uniforms);
+    let limb = 1.0 / CORONA_SCALE;
+    let glow = if distance < limb {
+        (distance / limb).powf(6.0)
+    } else {
+        ((1.0 - distance) / (1.0 - limb)).powf(2.0)
+    };
+
+    let x = fragment.vertex_position.x * 8.0 + uniforms.time as f32 * 0.05;
+    let y = fragment.vertex_position.y * 8.0;
+    let flicker = 0.8 + 0.2 * uniforms.noise_open_simplex.get_noise_2d(x, y);
+
+    Color::new(255, 170, 60).with_alpha(glow * flicker)
+}
+
+// Radio del aro que marca el cuerpo seguido por la cámara respecto al del cuerpo
+pub const HIGHLIGHT_SCALE: f32 = 1.25;
+
+// Aro que marca el cuerpo seguido por la cámara: una banda justo por fuera de su borde que
+// late con el tiempo (para mezcla aditiva)
+fn highlight_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let distance = view_rim(fragment, uniforms);
+    let limb = 1.0 / HIGHLIGHT_SCALE;
+    // 0 en el borde del cuerpo y en el del aro, 1 a mitad de camino
+    let band = 1.0 - ((distance - limb) / (1.0 - limb) * 2.0 - 1.0).abs();
+    let pulse = 0.75 + 0.25 * (uniforms.time as f32 * 0.1).sin();
+
+    Color::new(120, 200, 255).with_alpha(band.max(0.0).powi(2) * pulse)
+}
+
+// Colores del material del modelo (.mtl): el difuso llega interpolado en fragment.color
+fn material_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let Some(material) = &uniforms.material else {
+        return shade(fragment.color, &ROCK_MATERIAL, fragment, uniforms);
+    };
+
+    // Kd se multiplica por map_Kd cuando el material tiene textura
+    let albedo = match &uniforms.texture {
+        Some(texture) => fragment.color.blend_multiply(
+            &texture.sample_grad(fragment.tex_coords, fragment.uv_dx, fragment.uv_dy),
+        ),
+        None => fragment.color,
+    };
+    shade(albedo, &material.lighting(), fragment, uniforms)
+}
 
 fn jet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let zoom = 50.0;
@@ -366,44 +536,64 @@ fn jet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let highlight_color = Color::new(255, 255, 255); // Blanco para reflejos
 
     // Mezclar los colores usando el patrón metálico
-    let blended_color = base_color.lerp(&highlight_color, metallic_pattern * 0.5);
+    base_color.lerp(&highlight_color, metallic_pattern * 0.5)
+}
 
-    // Luz ambiental para todas las partes
-    let ambient_color = Color::new(50, 50, 80);
 
-    // Luz especular para reflejos dinámicos
-    let light_direction = Vec3::new(0.0, 1.0, -1.0).normalize();
-    let normal = fragment.normal.normalize();
-    let specular_intensity = ((normal.dot(&light_direction)).max(0.0).powf(16.0) * 0.8)
-        .clamp(0.0, 1.0); // Reflejos especulares suaves
-    let specular_color = Color::new(255, 255, 255) * specular_intensity;
 
-    // Sumar los efectos de iluminación
-    let final_color = blended_color
-        .blend_add(&ambient_color) // Añadir luz ambiental
-        .blend_add(&specular_color) // Añadir luz especular
-        * fragment.intensity; // Escalar por la intensidad del fragmento
+pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: &str) -> Color {
+    let Some((albedo_shader, material)) = lit_surface(shader_type) else {
+        return unlit_shader(fragment, uniforms, shader_type);
+    };
 
-    final_color
+    // Con textura se mezcla con el albedo procedural; con texture_blend = 1.0 lo reemplaza
+    let albedo = match &uniforms.texture {
+        Some(texture) => {
+            let texture_color = texture.sample_grad(fragment.tex_coords, fragment.uv_dx, fragment.uv_dy);
+            if uniforms.texture_blend >= 1.0 {
+                texture_color
+            } else {
+                albedo_shader(fragment, uniforms).lerp(&texture_color, uniforms.texture_blend)
+            }
+        }
+        None => albedo_shader(fragment, uniforms),
+    };
+    shade(albedo, &material, fragment, uniforms)
 }
 
+type AlbedoShader = fn(&Fragment, &Uniforms) -> Color;
+
+// Cuerpos iluminados por el Sol: su shader procedural solo da el color de la superficie y el
+// material decide cómo responde a la luz
+fn lit_surface(shader_type: &str) -> Option<(AlbedoShader, SurfaceMaterial)> {
+    let surface: (AlbedoShader, SurfaceMaterial) = match shader_type {
+        "volcanic_planet_shader" => (volcanic_planet_shader, VOLCANIC_MATERIAL),
+        "crystal_planet_shader" => (crystal_planet_shader, CRYSTAL_MATERIAL),
+        "vortex_planet_shader" => (vortex_planet_shader, VORTEX_MATERIAL),
+        "ringed_planet" => (ringed_planet, RINGED_MATERIAL),
+        "moon_shader" => (moon_shader, ROCK_MATERIAL),
+        "rocky_planet" => (rocky_planet, ROCK_MATERIAL),
+        "earth_like_planet_shader" => (earth_like_planet_shader, EARTH_MATERIAL),
+        "gas_giant_shader" => (gas_giant_shader, GAS_GIANT_MATERIAL),
+        "jet_shader" => (jet_shader, JET_MATERIAL),
+        _ => return None,
+    };
+    Some(surface)
+}
 
-
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: &str) -> Color {
+// Shaders que calculan su propio color: el Sol y la lava brillan por sí mismos, y el resto son
+// efectos translúcidos, líneas o shaders de prueba
+fn unlit_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: &str) -> Color {
     match shader_type {
         "solar_surface" => solar_shader(fragment, uniforms),
-        "volcanic_planet_shader" => volcanic_planet_shader(fragment, uniforms),
         "molten_core_planet_shader" => molten_core_planet_shader(fragment, uniforms),
-        "crystal_planet_shader" => crystal_planet_shader(fragment, uniforms),
-        "vortex_planet_shader" => vortex_planet_shader(fragment, uniforms), 
-        "ringed_planet" => ringed_planet(fragment, uniforms),
         "ring_shader" => ring_shader(fragment, uniforms),
-        "moon_shader" => moon_shader(fragment, uniforms),
-        "rocky_planet" => rocky_planet(fragment, uniforms),
-        "earth_like_planet_shader" => earth_like_planet_shader(fragment, uniforms),
-        "gas_giant_shader" => gas_giant_shader(fragment, uniforms), 
         "orbit_shader" => orbit_shader(fragment, uniforms), 
-        "jet_shader" => jet_shader(fragment, uniforms),
+        "uv_checker_shader" => uv_checker_shader(fragment, uniforms),
+        "material_shader" => material_shader(fragment, uniforms),
+        "atmosphere_shader" => atmosphere_shader(fragment, uniforms),
+        "corona_shader" => corona_shader(fragment, uniforms),
+        "highlight_shader" => highlight_shader(fragment, uniforms),
         _ => Color::new(0, 0, 0),
     }
-}
\ No newline at end of file
+}
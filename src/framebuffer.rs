@@ -1,39 +1,349 @@
+use std::path::Path;
+use std::thread;
+use std::ops::AddAssign;
+use image::{ImageFormat, ImageResult, Rgb, RgbImage};
+use crate::bloom::Bloom;
+use crate::color::Color;
+use crate::postprocess::{PostChain, PostFrame};
+use crate::rasterizer::{RasterScratch, TILE_SIZE};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthPass {
+    // Prueba y escritura de profundidad normales
+    Standard,
+    // Pre-pase: solo se escribe la profundidad, sin sombrear
+    DepthOnly,
+    // Después del pre-pase: solo se sombrea el fragmento más cercano de cada píxel
+    // (con profundidades empatadas gana el último en dibujarse)
+    Shading,
+}
+
+// Cómo se combina el color de un fragmento (src) con el que ya está en el buffer (dst)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    // Reemplaza el color; geometría opaca
+    Opaque,
+    // src * a + dst * (1 - a)
+    Alpha,
+    // dst + src * a
+    Additive,
+    // src + dst * (1 - a), con src ya multiplicado por su alfa
+    Premultiplied,
+}
+
+impl BlendMode {
+    pub fn blend(self, dst: Color, src: Color) -> Color {
+        let alpha = src.a();
+        match self {
+            BlendMode::Opaque => src,
+            BlendMode::Alpha => dst.lerp(&src, alpha),
+            BlendMode::Additive => dst + src * alpha,
+            BlendMode::Premultiplied => src + dst * (1.0 - alpha),
+        }
+    }
+}
+
+// Operador que lleva el color HDR del buffer de render al rango [0, 1] de la pantalla
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMapping {
+    // Satura cada canal en 1.0, como antes del buffer HDR
+    Clamp,
+    // x / (1 + x)
+    Reinhard,
+    // Curva fílmica ACES: contraste en los tonos medios y saturación suave de las luces
+    Aces,
+}
+
+// Ajuste de Narkowicz (2015) a la curva ACES, aplicado por canal
+fn aces(x: f32) -> f32 {
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+impl ToneMapping {
+    pub fn next(self) -> Self {
+        match self {
+            ToneMapping::Clamp => ToneMapping::Reinhard,
+            ToneMapping::Reinhard => ToneMapping::Aces,
+            ToneMapping::Aces => ToneMapping::Clamp,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapping::Clamp => "clamp",
+            ToneMapping::Reinhard => "Reinhard",
+            ToneMapping::Aces => "ACES",
+        }
+    }
+}
+
+// Vistas de depuración. Todas menos el wireframe reemplazan la imagen: se muestran sin
+// bloom, mapeo de tonos ni post-proceso
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugView {
+    Off,
+    // Aristas de los triángulos dibujadas sobre la escena
+    Wireframe,
+    // Normal de cada cara como color: (x, y, z) de [-1, 1] a (r, g, b) de [0, 1]
+    Normals,
+    // Profundidad en grises: lo más cercano en blanco, lo más lejano en gris oscuro
+    Depth,
+    // Veces que se sombreó cada píxel: negro, azul, verde, amarillo, rojo y blanco desde 5
+    Overdraw,
+    // Un color distinto por cada objeto dibujado
+    Objects,
+}
+
+impl DebugView {
+    pub fn next(self) -> Self {
+        match self {
+            DebugView::Off => DebugView::Wireframe,
+            DebugView::Wireframe => DebugView::Normals,
+            DebugView::Normals => DebugView::Depth,
+            DebugView::Depth => DebugView::Overdraw,
+            DebugView::Overdraw => DebugView::Objects,
+            DebugView::Objects => DebugView::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Off => "off",
+            DebugView::Wireframe => "wireframe",
+            DebugView::Normals => "normals",
+            DebugView::Depth => "depth",
+            DebugView::Overdraw => "overdraw",
+            DebugView::Objects => "objects",
+        }
+    }
+
+    fn replaces_image(self) -> bool {
+        !matches!(self, DebugView::Off | DebugView::Wireframe)
+    }
+}
+
+// Lo que suma cada sombreado en la vista de overdraw (se cuenta en el canal rojo)
+pub const OVERDRAW_STEP: Color = Color::from_f32(1.0, 0.0, 0.0);
+
+const HEAT: [Color; 6] = [
+    Color::from_f32(0.0, 0.0, 0.0),
+    Color::from_f32(0.0, 0.25, 1.0),
+    Color::from_f32(0.0, 0.9, 0.3),
+    Color::from_f32(1.0, 0.9, 0.0),
+    Color::from_f32(1.0, 0.15, 0.0),
+    Color::from_f32(1.0, 1.0, 1.0),
+];
+
+// Color del mapa de calor para `count` sombreados (fraccionario con anti-aliasing)
+fn heat_color(count: f32) -> Color {
+    let position = count.clamp(0.0, (HEAT.len() - 1) as f32);
+    let index = (position as usize).min(HEAT.len() - 2);
+    HEAT[index].lerp(&HEAT[index + 1], position - index as f32)
+}
+
+// Color de la vista de objetos: tonos separados por la razón áurea para que dos objetos
+// seguidos nunca se parezcan
+pub fn object_color(id: u32) -> Color {
+    let hue = (id as f32 * 0.618_034).fract() * 6.0;
+    let channel = |offset: f32| {
+        let distance = ((hue + offset) % 6.0 - 3.0).abs();
+        0.25 + 0.75 * (distance - 1.0).clamp(0.0, 1.0)
+    };
+    Color::from_f32(channel(0.0), channel(4.0), channel(2.0))
+}
+
+// Modos de anti-aliasing. Con SSAA el fragment shader se ejecuta en cada muestra; con MSAA
+// una vez por píxel y el color se escribe en las muestras cubiertas que pasan la profundidad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AntiAliasing {
+    Off,
+    // 4 muestras en cuadrícula ordenada de 2x2
+    Ssaa4x,
+    // 4 muestras en cuadrícula rotada
+    Msaa4x,
+}
+
+// Posiciones de las muestras dentro del píxel (0..1)
+const CENTER_SAMPLE: [(f32, f32); 1] = [(0.5, 0.5)];
+const ORDERED_GRID_4X: [(f32, f32); 4] = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)];
+const ROTATED_GRID_4X: [(f32, f32); 4] = [(0.375, 0.125), (0.875, 0.375), (0.125, 0.625), (0.625, 0.875)];
+
+impl AntiAliasing {
+    pub fn sample_offsets(self) -> &'static [(f32, f32)] {
+        match self {
+            AntiAliasing::Off => &CENTER_SAMPLE,
+            AntiAliasing::Ssaa4x => &ORDERED_GRID_4X,
+            AntiAliasing::Msaa4x => &ROTATED_GRID_4X,
+        }
+    }
+
+    pub fn sample_count(self) -> usize {
+        self.sample_offsets().len()
+    }
+
+    pub fn per_sample_shading(self) -> bool {
+        self == AntiAliasing::Ssaa4x
+    }
+
+    // Siguiente modo, para alternarlos con una tecla
+    pub fn next(self) -> Self {
+        match self {
+            AntiAliasing::Off => AntiAliasing::Ssaa4x,
+            AntiAliasing::Ssaa4x => AntiAliasing::Msaa4x,
+            AntiAliasing::Msaa4x => AntiAliasing::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AntiAliasing::Off => "off",
+            AntiAliasing::Ssaa4x => "SSAA 4x",
+            AntiAliasing::Msaa4x => "MSAA 4x",
+        }
+    }
+}
+
+// Contadores de fragmentos (triángulos y puntos) durante un cuadro
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderStats {
+    pub fragments: u64,
+    pub shaded: u64,
+    pub written: u64,
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: RenderStats) {
+        self.fragments += other.fragments;
+        self.shaded += other.shaded;
+        self.written += other.written;
+    }
+}
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
+    // Imagen final que se muestra, ya con el mapeo de tonos
     pub buffer: Vec<u32>,
+    // Color HDR lineal y profundidad de cada píxel, escritos por `resolve`
+    pub hdr_buffer: Vec<Color>,
     pub zbuffer: Vec<f32>,
     background_color: u32,
     current_color: u32,
+    thread_count: usize,
+    early_z: bool,
+    depth_prepass: bool,
+    depth_pass: DepthPass,
+    anti_aliasing: AntiAliasing,
+    blend_mode: BlendMode,
+    depth_write: bool,
+    bloom: bool,
+    bloom_pass: Bloom,
+    tone_mapping: ToneMapping,
+    // En pasos (stops): cada unidad duplica el brillo antes del mapeo de tonos
+    exposure: f32,
+    post_chain: PostChain,
+    debug_view: DebugView,
+    // Identificador del próximo objeto (llamada a rasterize) del cuadro
+    next_object: u32,
+    // Color ya mapeado a [0, 1] sobre el que trabaja el post-proceso, antes de pasar a `buffer`
+    display_buffer: Vec<Color>,
+    // Color y profundidad donde se dibuja, por muestra. Se guardan tile por tile (cada tile
+    // de TILE_SIZE x TILE_SIZE completo, aunque salga de la pantalla), así el rasterizador
+    // entrega a cada hilo su tile sin copiarlo; dentro del tile van píxel a píxel con
+    // `sample_count` muestras seguidas
+    tiles_x: usize,
+    sample_buffer: Vec<Color>,
+    sample_zbuffer: Vec<f32>,
+    pub stats: RenderStats,
+    // Memoria intermedia del rasterizador, reutilizada entre llamadas a render
+    pub scratch: RasterScratch,
 }
 
 impl Framebuffer {
     pub fn new(width: usize, height: usize) -> Self {
-        Framebuffer {
+        let mut framebuffer = Framebuffer {
             width,
             height,
             buffer: vec![0; width * height],
+            hdr_buffer: vec![Color::black(); width * height],
             zbuffer: vec![f32::INFINITY; width * height],
             background_color: 0x000000,
             current_color: 0xFFFFFF,
-        }
+            thread_count: thread::available_parallelism().map_or(1, |n| n.get()),
+            early_z: true,
+            depth_prepass: false,
+            depth_pass: DepthPass::Standard,
+            anti_aliasing: AntiAliasing::Off,
+            blend_mode: BlendMode::Opaque,
+            depth_write: true,
+            bloom: true,
+            bloom_pass: Bloom::default(),
+            tone_mapping: ToneMapping::Aces,
+            exposure: 0.0,
+            post_chain: PostChain::new(),
+            debug_view: DebugView::Off,
+            next_object: 0,
+            display_buffer: vec![Color::black(); width * height],
+            tiles_x: width.div_ceil(TILE_SIZE),
+            sample_buffer: Vec::new(),
+            sample_zbuffer: Vec::new(),
+            stats: RenderStats::default(),
+            scratch: RasterScratch::default(),
+        };
+        framebuffer.set_anti_aliasing(AntiAliasing::Off);
+        framebuffer
     }
 
+    // buffer, hdr_buffer y zbuffer no se limpian: `resolve` los reescribe completos
     pub fn clear(&mut self) {
-        for pixel in self.buffer.iter_mut() {
-            *pixel = self.background_color;
-        }
-        for depth in self.zbuffer.iter_mut() {
-            *depth = f32::INFINITY;
-        }
+        self.sample_buffer.fill(Color::from_hex(self.background_color));
+        self.sample_zbuffer.fill(f32::INFINITY);
+        self.depth_pass = DepthPass::Standard;
+        self.next_object = 0;
+        self.stats = RenderStats::default();
     }
 
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
         if x < self.width && y < self.height {
-            let index = y * self.width + x;
-            if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
-                self.zbuffer[index] = depth;
+            let samples = self.anti_aliasing.sample_count();
+            let index = self.sample_index(x, y);
+            let color = Color::from_hex(self.current_color);
+            let (pass, blend_mode, depth_write) = (self.depth_pass, self.blend_mode, self.depth_write);
+            let overdraw = self.debug_view == DebugView::Overdraw;
+            let write = |dst: Color| if overdraw { dst + OVERDRAW_STEP } else { blend_mode.blend(dst, color) };
+            let (buffer, zbuffer) = self.raster_buffers_mut();
+
+            // Un punto cubre todas las muestras de su píxel
+            let mut written = false;
+            for sample in index..index + samples {
+                match pass {
+                    DepthPass::Standard => {
+                        if zbuffer[sample] > depth {
+                            buffer[sample] = write(buffer[sample]);
+                            if depth_write {
+                                zbuffer[sample] = depth;
+                            }
+                            written = true;
+                        }
+                    }
+                    DepthPass::DepthOnly => {
+                        if zbuffer[sample] > depth {
+                            zbuffer[sample] = depth;
+                        }
+                    }
+                    DepthPass::Shading => {
+                        if zbuffer[sample] >= depth {
+                            buffer[sample] = write(buffer[sample]);
+                            written = true;
+                        }
+                    }
+                }
+            }
+
+            self.stats.fragments += 1;
+            if written {
+                self.stats.shaded += 1;
+                self.stats.written += 1;
             }
         }
     }
@@ -41,4 +351,304 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
-}
\ No newline at end of file
+
+    // Hilos usados para rasterizar; 1 usa el camino secuencial
+    pub fn set_thread_count(&mut self, threads: usize) {
+        self.thread_count = threads.max(1);
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    // Probar la profundidad antes de ejecutar el fragment shader
+    pub fn set_early_z(&mut self, enabled: bool) {
+        self.early_z = enabled;
+    }
+
+    pub fn early_z(&self) -> bool {
+        self.early_z
+    }
+
+    // Dibujar primero solo la profundidad de toda la escena y sombrear después
+    pub fn set_depth_prepass(&mut self, enabled: bool) {
+        self.depth_prepass = enabled;
+    }
+
+    pub fn depth_prepass(&self) -> bool {
+        self.depth_prepass
+    }
+
+    pub fn set_depth_pass(&mut self, pass: DepthPass) {
+        self.depth_pass = pass;
+        // El pase de sombreado recorre los mismos objetos que el pre-pase, en el mismo orden
+        if pass == DepthPass::Shading {
+            self.next_object = 0;
+        }
+    }
+
+    pub fn depth_pass(&self) -> DepthPass {
+        self.depth_pass
+    }
+
+    // Mezcla de las siguientes escrituras de color (puntos y triángulos)
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    // Con false se prueba la profundidad pero no se escribe (geometría transparente)
+    pub fn set_depth_write(&mut self, enabled: bool) {
+        self.depth_write = enabled;
+    }
+
+    pub fn depth_write(&self) -> bool {
+        self.depth_write
+    }
+
+    // Resplandor alrededor de lo que brilla más que el blanco, aplicado en `resolve`
+    pub fn set_bloom(&mut self, enabled: bool) {
+        self.bloom = enabled;
+    }
+
+    pub fn bloom(&self) -> bool {
+        self.bloom
+    }
+
+    // Efectos de pantalla completa aplicados después del mapeo de tonos, en orden
+    pub fn set_post_chain(&mut self, post_chain: PostChain) {
+        self.post_chain = post_chain;
+    }
+
+    pub fn post_chain(&self) -> &PostChain {
+        &self.post_chain
+    }
+
+    pub fn post_chain_mut(&mut self) -> &mut PostChain {
+        &mut self.post_chain
+    }
+
+    pub fn set_debug_view(&mut self, view: DebugView) {
+        self.debug_view = view;
+    }
+
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_view
+    }
+
+    // Identificador para la vista de objetos; cada llamada a rasterize dibuja un objeto
+    pub fn next_object_id(&mut self) -> u32 {
+        self.next_object += 1;
+        self.next_object - 1
+    }
+
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    pub fn tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping
+    }
+
+    pub fn set_exposure(&mut self, stops: f32) {
+        self.exposure = stops;
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    // Cambia el modo de anti-aliasing y crea los buffers con las muestras de cada píxel
+    pub fn set_anti_aliasing(&mut self, mode: AntiAliasing) {
+        self.anti_aliasing = mode;
+        let tiles_y = self.height.div_ceil(TILE_SIZE);
+        let samples = self.tiles_x * tiles_y * TILE_SIZE * TILE_SIZE * mode.sample_count();
+        self.sample_buffer = vec![Color::from_hex(self.background_color); samples];
+        self.sample_zbuffer = vec![f32::INFINITY; samples];
+    }
+
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        self.anti_aliasing
+    }
+
+    // Posición de la primera muestra del píxel en los buffers del rasterizador
+    pub fn sample_index(&self, x: usize, y: usize) -> usize {
+        let tile = (y / TILE_SIZE) * self.tiles_x + x / TILE_SIZE;
+        let pixel = (y % TILE_SIZE) * TILE_SIZE + x % TILE_SIZE;
+        (tile * TILE_SIZE * TILE_SIZE + pixel) * self.anti_aliasing.sample_count()
+    }
+
+    // Buffers donde dibuja el rasterizador, ordenados por tiles (ver `sample_index`)
+    pub fn raster_buffers_mut(&mut self) -> (&mut [Color], &mut [f32]) {
+        (&mut self.sample_buffer, &mut self.sample_zbuffer)
+    }
+
+    // Lleva lo dibujado a los buffers de la pantalla: promedia las muestras de cada píxel en
+    // `hdr_buffer` (`zbuffer` recibe la más cercana), agrega el bloom si está activo y
+    // escribe en `buffer` el resultado del mapeo de tonos con la exposición actual, pasado
+    // por la cadena de post-proceso
+    pub fn resolve(&mut self) {
+        self.resolve_samples();
+
+        if self.debug_view.replaces_image() {
+            self.show_debug_view();
+            return;
+        }
+
+        if self.bloom {
+            self.bloom_pass.apply(&mut self.hdr_buffer, self.width, self.height);
+        }
+
+        match self.tone_mapping {
+            ToneMapping::Clamp => self.tone_map(|x| x),
+            ToneMapping::Reinhard => self.tone_map(|x| x / (1.0 + x)),
+            ToneMapping::Aces => self.tone_map(aces),
+        }
+    }
+
+    fn resolve_samples(&mut self) {
+        let samples = self.anti_aliasing.sample_count();
+        let weight = 1.0 / samples as f32;
+
+        // Se recorre fila por fila dentro de cada tile, donde las muestras están seguidas
+        for y in 0..self.height {
+            for tile_x in 0..self.tiles_x {
+                let x = tile_x * TILE_SIZE;
+                let width = TILE_SIZE.min(self.width - x);
+                let start = self.sample_index(x, y);
+                let colors = self.sample_buffer[start..start + width * samples].chunks_exact(samples);
+                let depths = self.sample_zbuffer[start..start + width * samples].chunks_exact(samples);
+
+                let row = y * self.width + x..y * self.width + x + width;
+                let resolved = self.hdr_buffer[row.clone()].iter_mut().zip(&mut self.zbuffer[row]);
+                for ((hdr, depth), (colors, depths)) in resolved.zip(colors.zip(depths)) {
+                    *hdr = if samples == 1 {
+                        colors[0]
+                    } else {
+                        colors.iter().fold(Color::from_f32(0.0, 0.0, 0.0), |sum, &color| sum + color) * weight
+                    };
+                    *depth = depths.iter().copied().fold(f32::INFINITY, f32::min);
+                }
+            }
+        }
+    }
+
+    // Un bucle por operador para que no haya que elegirlo en cada píxel
+    fn tone_map(&mut self, operator: impl Fn(f32) -> f32) {
+        let scale = self.exposure.exp2();
+        let map = |color: &Color| {
+            Color::from_f32(operator(color.r() * scale), operator(color.g() * scale), operator(color.b() * scale))
+        };
+
+        if !self.post_chain.is_active() {
+            for (pixel, color) in self.buffer.iter_mut().zip(&self.hdr_buffer) {
+                *pixel = map(color).to_hex();
+            }
+            return;
+        }
+
+        for (display, color) in self.display_buffer.iter_mut().zip(&self.hdr_buffer) {
+            *display = map(color);
+        }
+        self.post_chain.apply(&mut PostFrame {
+            color: &mut self.display_buffer,
+            depth: &self.zbuffer,
+            width: self.width,
+            height: self.height,
+        });
+        for (pixel, color) in self.buffer.iter_mut().zip(&self.display_buffer) {
+            *pixel = color.to_hex();
+        }
+    }
+
+    // Las vistas de normales y objetos ya se sombrearon con sus colores; profundidad y
+    // overdraw se calculan aquí a partir de lo resuelto
+    fn show_debug_view(&mut self) {
+        match self.debug_view {
+            DebugView::Depth => {
+                let finite = self.zbuffer.iter().copied().filter(|depth| depth.is_finite());
+                let (near, far) = finite.fold((f32::INFINITY, f32::NEG_INFINITY), |(near, far), depth| {
+                    (near.min(depth), far.max(depth))
+                });
+                let range = (far - near).max(f32::EPSILON);
+                for (hdr, &depth) in self.hdr_buffer.iter_mut().zip(&self.zbuffer) {
+                    *hdr = if depth.is_finite() {
+                        let gray = 1.0 - 0.8 * (depth - near) / range;
+                        Color::from_f32(gray, gray, gray)
+                    } else {
+                        Color::black()
+                    };
+                }
+            }
+            DebugView::Overdraw => {
+                for hdr in self.hdr_buffer.iter_mut() {
+                    *hdr = heat_color(hdr.r());
+                }
+            }
+            _ => {}
+        }
+
+        for (pixel, color) in self.buffer.iter_mut().zip(&self.hdr_buffer) {
+            *pixel = color.to_hex();
+        }
+    }
+
+    // Píxeles cubiertos por alguna geometría en este cuadro
+    pub fn covered_pixels(&self) -> usize {
+        self.zbuffer.iter().filter(|depth| depth.is_finite()).count()
+    }
+
+    // Fragmentos sombreados por cada píxel cubierto (1.0 = sin sobre-dibujado)
+    pub fn overdraw(&self) -> f32 {
+        let covered = self.covered_pixels();
+        if covered == 0 {
+            0.0
+        } else {
+            self.stats.shaded as f32 / covered as f32
+        }
+    }
+
+    pub fn to_image(&self) -> RgbImage {
+        RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let pixel = self.buffer[y as usize * self.width + x as usize];
+            Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+        })
+    }
+
+    pub fn save_png(&self, path: &Path) -> ImageResult<()> {
+        self.to_image().save_with_format(path, ImageFormat::Png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_mapping_operators() {
+        let tone_map = |tone_mapping: ToneMapping, exposure: f32, color: Color| {
+            let mut framebuffer = Framebuffer::new(1, 1);
+            framebuffer.set_tone_mapping(tone_mapping);
+            framebuffer.set_exposure(exposure);
+            framebuffer.raster_buffers_mut().0[0] = color;
+            framebuffer.resolve();
+            let hex = framebuffer.buffer[0];
+            ((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+        };
+        // Four times brighter than white in red
+        let radiance = Color::from_f32(4.0, 1.0, 0.25);
+
+        assert_eq!(tone_map(ToneMapping::Clamp, 0.0, radiance), (255, 255, 64));
+        assert_eq!(tone_map(ToneMapping::Reinhard, 0.0, radiance), (204, 128, 51));
+        assert_eq!(tone_map(ToneMapping::Aces, 0.0, radiance), (248, 205, 95));
+
+        // Each stop of exposure doubles the radiance
+        assert_eq!(
+            tone_map(ToneMapping::Reinhard, -2.0, radiance),
+            tone_map(ToneMapping::Reinhard, 0.0, radiance * 0.25)
+        );
+    }
+}
@@ -0,0 +1,559 @@
+// Golden-image regression tests: canonical scenes are rendered offscreen and compared
+// against the reference PNGs in `tests/golden`. Run with `UPDATE_GOLDEN=1 cargo test`
+// to (re)generate the references after an intentional visual change.
+
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use image::{Rgb, RgbImage};
+use nalgebra_glm::{self as glm, Mat4, Vec3};
+use crate::framebuffer::{AntiAliasing, BlendMode, DebugView, DepthPass, Framebuffer};
+use crate::obj::Obj;
+use crate::triangle::CullMode;
+use crate::skybox::Skybox;
+use crate::texture::{Texture, WrapMode};
+use crate::color::Color;
+use crate::lighting::{Light, LightingModel, Occluder, Shadows};
+use crate::fragment::HIGHLIGHT_SCALE;
+use crate::camera::Camera;
+use crate::{create_model_matrix, initial_camera, render, render_frame, Scene, Uniforms, STAR};
+use crate::{
+    create_cellular_noise, create_open_simplex_noise, create_perlin_noise, create_value_cubic_noise,
+    create_value_noise,
+};
+
+const SIZE: usize = 96;
+const TIME: u32 = 20;
+// Maximum difference allowed per color channel before a pixel counts as changed
+const TOLERANCE: u8 = 2;
+
+const SPHERE_SHADERS: [&str; 14] = [
+    "solar_surface",
+    "volcanic_planet_shader",
+    "molten_core_planet_shader",
+    "crystal_planet_shader",
+    "vortex_planet_shader",
+    "ringed_planet",
+    "ring_shader",
+    "moon_shader",
+    "rocky_planet",
+    "earth_like_planet_shader",
+    "gas_giant_shader",
+    "orbit_shader",
+    "jet_shader",
+    "uv_checker_shader",
+];
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn diff_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target").join("golden-diff")
+}
+
+fn scene_uniforms(model_matrix: Mat4, cull_mode: CullMode) -> Uniforms {
+    Uniforms {
+        model_matrix,
+        view_matrix: Mat4::identity(),
+        camera_position: Vec3::zeros(),
+        projection_matrix: Mat4::identity(),
+        viewport_matrix: Mat4::identity(),
+        normal_matrix: model_matrix.try_inverse().unwrap().transpose(),
+        time: TIME,
+        noise_open_simplex: Arc::new(create_open_simplex_noise()),
+        noise_cellular: Arc::new(create_cellular_noise()),
+        noise_perlin: Arc::new(create_perlin_noise()),
+        noise_value: Arc::new(create_value_noise()),
+        noise_value_cubic: Arc::new(create_value_cubic_noise()),
+        cull_mode,
+        frustum_clip: false,
+        texture: None,
+        texture_blend: 0.0,
+        material: None,
+        light: front_light(),
+        lighting_model: LightingModel::BlinnPhong,
+        shadows: Arc::new(Shadows::new()),
+    }
+}
+
+// Light straight from the camera and far enough away to act as a directional light
+fn front_light() -> Light {
+    Light::new(Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, -1.0e6), Color::from_f32(1.0, 1.0, 1.0), 1.0)
+}
+
+fn render_with(model: &str, shader: &str, uniforms: &Uniforms) -> Framebuffer {
+    let obj = Obj::load(&format!("{}/assets/{}", env!("CARGO_MANIFEST_DIR"), model))
+        .expect("Failed to load golden scene model");
+    let vertex_array = obj.get_vertex_array();
+
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.clear();
+    render(&mut framebuffer, uniforms, &vertex_array, shader);
+    framebuffer.resolve();
+    framebuffer
+}
+
+// Model centered on screen with the identity view/projection used by the scene
+fn render_model(model: &str, shader: &str, scale: f32, rotation: Vec3, cull_mode: CullMode) -> Framebuffer {
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let model_matrix = create_model_matrix(center, scale, rotation);
+    render_with(model, shader, &scene_uniforms(model_matrix, cull_mode))
+}
+
+// Unit sphere seen through a real perspective projection from `eye`, with frustum clipping
+fn render_perspective(eye: Vec3, near: f32, shader: &str, cull_mode: CullMode) -> Framebuffer {
+    let model_matrix = create_model_matrix(Vec3::zeros(), 1.0, Vec3::new(0.3, 0.0, 0.5));
+    let mut uniforms = scene_uniforms(model_matrix, cull_mode);
+    uniforms.view_matrix = glm::look_at(&eye, &Vec3::zeros(), &Vec3::y());
+    uniforms.camera_position = eye;
+    uniforms.projection_matrix = glm::perspective(1.0, 60.0_f32.to_radians(), near, 10.0);
+    uniforms.viewport_matrix = glm::translation(&Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0))
+        * glm::scaling(&Vec3::new(SIZE as f32 / 2.0, -(SIZE as f32) / 2.0, 1.0));
+    uniforms.frustum_clip = true;
+    render_with("spheresmooth.obj", shader, &uniforms)
+}
+
+fn channel_diff(a: &Rgb<u8>, b: &Rgb<u8>) -> u8 {
+    (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap_or(0)
+}
+
+// Compares against the reference image and returns a description of the mismatch, if any
+fn check_golden(name: &str, framebuffer: &Framebuffer) -> Option<String> {
+    let actual = framebuffer.to_image();
+    let reference_path = golden_dir().join(format!("{}.png", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&reference_path).unwrap();
+        return None;
+    }
+
+    let expected = match image::open(&reference_path) {
+        Ok(image) => image.to_rgb8(),
+        Err(err) => {
+            return Some(format!(
+                "{}: cannot read {} ({}); run with UPDATE_GOLDEN=1 to create it",
+                name,
+                reference_path.display(),
+                err
+            ))
+        }
+    };
+
+    if expected.dimensions() != actual.dimensions() {
+        return Some(format!(
+            "{}: size {:?} does not match reference {:?}",
+            name,
+            actual.dimensions(),
+            expected.dimensions()
+        ));
+    }
+
+    // Changed pixels are painted red over a dimmed copy of the reference
+    let mut mismatches = 0;
+    let diff = RgbImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let a = actual.get_pixel(x, y);
+        let e = expected.get_pixel(x, y);
+        if channel_diff(a, e) > TOLERANCE {
+            mismatches += 1;
+            Rgb([255, 0, 0])
+        } else {
+            Rgb([e[0] / 4, e[1] / 4, e[2] / 4])
+        }
+    });
+
+    if mismatches == 0 {
+        return None;
+    }
+
+    std::fs::create_dir_all(diff_dir()).unwrap();
+    let actual_path = diff_dir().join(format!("{}.actual.png", name));
+    let diff_path = diff_dir().join(format!("{}.diff.png", name));
+    actual.save(&actual_path).unwrap();
+    diff.save(&diff_path).unwrap();
+
+    Some(format!(
+        "{}: {} pixels differ by more than {} (see {})",
+        name,
+        mismatches,
+        TOLERANCE,
+        diff_path.display()
+    ))
+}
+
+fn assert_no_failures(failures: Vec<String>) {
+    assert!(failures.is_empty(), "golden image mismatches:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn sphere_shaders_match_golden_images() {
+    let failures = SPHERE_SHADERS
+        .iter()
+        .filter_map(|shader| {
+            let framebuffer = render_model("spheresmooth.obj", shader, 40.0, Vec3::new(0.3, 0.0, 0.5), CullMode::Back);
+            check_golden(&format!("sphere_{}", shader), &framebuffer)
+        })
+        .collect();
+
+    assert_no_failures(failures);
+}
+
+#[test]
+fn ring_matches_golden_image() {
+    let framebuffer = render_model("ring.obj", "ring_shader", 26.0, Vec3::new(1.2, 0.0, 0.3), CullMode::None);
+    assert_no_failures(check_golden("ring", &framebuffer).into_iter().collect());
+}
+
+#[test]
+fn jet_matches_golden_image() {
+    let framebuffer = render_model("jet.obj", "jet_shader", 4.0, Vec3::new(-1.0, 0.0, 0.0), CullMode::None);
+    assert_no_failures(check_golden("jet", &framebuffer).into_iter().collect());
+}
+
+#[test]
+fn jet_material_matches_golden_image() {
+    // Colors come from assets/jet.mtl instead of a hardcoded shader
+    let obj = Obj::load(&format!("{}/assets/jet.obj", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let meshes = obj.get_material_meshes();
+    assert_eq!(meshes.len(), 1);
+    assert_eq!(meshes[0].material.name, "Material");
+    assert_eq!(meshes[0].material.shininess, 16.0);
+
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let mut uniforms = scene_uniforms(create_model_matrix(center, 4.0, Vec3::new(-1.0, 0.0, 0.0)), CullMode::None);
+    uniforms.material = Some(meshes[0].material.clone());
+
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.clear();
+    render(&mut framebuffer, &uniforms, &meshes[0].vertices, "material_shader");
+    framebuffer.resolve();
+    assert_no_failures(check_golden("jet_material", &framebuffer).into_iter().collect());
+}
+
+#[test]
+fn anti_aliased_jet_matches_golden_images() {
+    let obj = Obj::load(&format!("{}/assets/jet.obj", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let vertex_array = obj.get_vertex_array();
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let uniforms = scene_uniforms(create_model_matrix(center, 4.0, Vec3::new(-1.0, 0.0, 0.0)), CullMode::None);
+
+    let draw = |anti_aliasing: AntiAliasing| {
+        let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+        framebuffer.set_anti_aliasing(anti_aliasing);
+        framebuffer.clear();
+        render(&mut framebuffer, &uniforms, &vertex_array, "jet_shader");
+        framebuffer.resolve();
+        framebuffer
+    };
+
+    let ssaa = draw(AntiAliasing::Ssaa4x);
+    let msaa = draw(AntiAliasing::Msaa4x);
+
+    // MSAA shades once per pixel, SSAA once per covered sample
+    assert!(msaa.stats.shaded * 2 < ssaa.stats.shaded);
+
+    let failures = [check_golden("jet_ssaa", &ssaa), check_golden("jet_msaa", &msaa)];
+    assert_no_failures(failures.into_iter().flatten().collect());
+}
+
+#[test]
+fn debug_views_match_golden_images() {
+    let load = |model: &str| {
+        Obj::load(&format!("{}/assets/{}", env!("CARGO_MANIFEST_DIR"), model)).unwrap().get_vertex_array()
+    };
+    let (jet, sphere) = (load("jet.obj"), load("spheresmooth.obj"));
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let jet_uniforms = scene_uniforms(create_model_matrix(center, 4.0, Vec3::new(-1.0, 0.0, 0.0)), CullMode::None);
+    // A second object beside the jet, closer to the camera
+    let sphere_center = Vec3::new(SIZE as f32 * 0.7, SIZE as f32 * 0.6, 20.0);
+    let sphere_uniforms = scene_uniforms(create_model_matrix(sphere_center, 16.0, Vec3::zeros()), CullMode::Back);
+
+    let draw = |view: DebugView| {
+        let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+        framebuffer.set_debug_view(view);
+        framebuffer.clear();
+        render(&mut framebuffer, &jet_uniforms, &jet, "jet_shader");
+        render(&mut framebuffer, &sphere_uniforms, &sphere, "rocky_planet");
+        framebuffer.resolve();
+        framebuffer
+    };
+
+    let views = [DebugView::Wireframe, DebugView::Normals, DebugView::Depth, DebugView::Overdraw, DebugView::Objects];
+    let failures = views
+        .iter()
+        .filter_map(|&view| check_golden(&format!("debug_{}", view.name()), &draw(view)))
+        .collect();
+    assert_no_failures(failures);
+}
+
+#[test]
+fn translucent_ring_matches_golden_image() {
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let load = |model: &str| {
+        Obj::load(&format!("{}/assets/{}", env!("CARGO_MANIFEST_DIR"), model)).unwrap().get_vertex_array()
+    };
+    let (sphere, ring) = (load("spheresmooth.obj"), load("ring.obj"));
+
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.clear();
+    let planet = scene_uniforms(create_model_matrix(center, 20.0, Vec3::new(0.3, 0.0, 0.5)), CullMode::Back);
+    render(&mut framebuffer, &planet, &sphere, "ringed_planet");
+    framebuffer.resolve();
+    let opaque_depth = framebuffer.zbuffer.clone();
+
+    // The planet must stay visible through the ring, and the ring must not write depth
+    framebuffer.set_blend_mode(BlendMode::Alpha);
+    framebuffer.set_depth_write(false);
+    let ring_uniforms = scene_uniforms(create_model_matrix(center, 26.0, Vec3::new(1.2, 0.0, 0.3)), CullMode::None);
+    render(&mut framebuffer, &ring_uniforms, &ring, "ring_shader");
+    framebuffer.resolve();
+
+    assert!(opaque_depth == framebuffer.zbuffer, "translucent geometry wrote depth");
+    assert_no_failures(check_golden("ring_translucent", &framebuffer).into_iter().collect());
+}
+
+#[test]
+fn highlight_matches_golden_image() {
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let sphere = Obj::load(&format!("{}/assets/spheresmooth.obj", env!("CARGO_MANIFEST_DIR"))).unwrap().get_vertex_array();
+
+    // A glowing band just outside the body's edge, added over it
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.clear();
+    let planet = scene_uniforms(create_model_matrix(center, 30.0, Vec3::new(0.3, 0.0, 0.5)), CullMode::Back);
+    render(&mut framebuffer, &planet, &sphere, "rocky_planet");
+    framebuffer.set_blend_mode(BlendMode::Additive);
+    framebuffer.set_depth_write(false);
+    let highlight = scene_uniforms(create_model_matrix(center, 30.0 * HIGHLIGHT_SCALE, Vec3::zeros()), CullMode::Back);
+    render(&mut framebuffer, &highlight, &sphere, "highlight_shader");
+    framebuffer.resolve();
+    assert_no_failures(check_golden("highlight", &framebuffer).into_iter().collect());
+}
+
+#[test]
+fn blend_modes() {
+    let dst = Color::from_hex(0x204060);
+    let src = Color::new(200, 100, 0).with_alpha(64.0 / 255.0);
+    let to_rgb = |color: Color| {
+        let hex = color.to_hex();
+        ((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+    };
+
+    assert_eq!(to_rgb(BlendMode::Opaque.blend(dst, src)), (200, 100, 0));
+    assert_eq!(to_rgb(BlendMode::Alpha.blend(dst, src)), (74, 73, 72));
+    assert_eq!(to_rgb(BlendMode::Additive.blend(dst, src)), (82, 89, 96));
+    assert_eq!(to_rgb(BlendMode::Premultiplied.blend(dst, src)), (224, 148, 72));
+}
+
+#[test]
+fn spheres_are_lit_from_the_light_position() {
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let brightness = |light_offset: Vec3| {
+        let mut uniforms = scene_uniforms(create_model_matrix(center, 40.0, Vec3::zeros()), CullMode::Back);
+        uniforms.light = Light::new(center + light_offset, Color::from_f32(1.0, 1.0, 1.0), 1.0);
+        let framebuffer = render_with("spheresmooth.obj", "gas_giant_shader", &uniforms);
+        let row = SIZE / 2 * SIZE;
+        (framebuffer.hdr_buffer[row + SIZE / 2 - 30].luminance(), framebuffer.hdr_buffer[row + SIZE / 2 + 30].luminance())
+    };
+
+    // A light in the orbital plane lights the half facing it and leaves the other half dark
+    let (left, right) = brightness(Vec3::new(1000.0, 0.0, 0.0));
+    assert!(right > 0.2 && left < 0.01, "left {} right {}", left, right);
+    let (left, right) = brightness(Vec3::new(-1000.0, 0.0, 0.0));
+    assert!(left > 0.2 && right < 0.01, "left {} right {}", left, right);
+}
+
+#[test]
+fn pbr_spheres_match_golden_images() {
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let mut uniforms = scene_uniforms(create_model_matrix(center, 40.0, Vec3::new(0.3, 0.0, 0.5)), CullMode::Back);
+    uniforms.lighting_model = LightingModel::Pbr;
+    // Light from the upper left so the highlight is off the center of the disc
+    uniforms.light.position = Vec3::new(-1.0e6, -1.0e6, -1.0e6);
+    let failures = ["earth_like_planet_shader", "jet_shader"]
+        .iter()
+        .filter_map(|shader| check_golden(&format!("pbr_{}", shader), &render_with("spheresmooth.obj", shader, &uniforms)))
+        .collect();
+    assert_no_failures(failures);
+}
+
+#[test]
+fn shadows_match_golden_images() {
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let render_shadowed = |shader: &str, occluder: Occluder| {
+        let mut uniforms = scene_uniforms(create_model_matrix(center, 40.0, Vec3::new(0.3, 0.0, 0.5)), CullMode::Back);
+        // The light comes from the upper left, a bit in front of the sphere
+        uniforms.light = Light::new(center + Vec3::new(-3000.0, -3000.0, -2000.0), Color::from_f32(1.0, 1.0, 1.0), 1.0)
+            .with_radius(150.0);
+        let mut shadows = Shadows::new();
+        shadows.add(occluder);
+        uniforms.shadows = Arc::new(shadows);
+        render_with("spheresmooth.obj", shader, &uniforms)
+    };
+
+    // A moon between the light and the planet, and a tilted ring around it
+    let moon = Occluder::Sphere { center: center + Vec3::new(-60.0, -60.0, -40.0), radius: 10.0 };
+    let ring = Occluder::Ring {
+        center,
+        normal: Vec3::new(1.0, -1.0, 1.0).normalize(),
+        inner_radius: 50.0,
+        outer_radius: 80.0,
+        opacity: 0.6,
+    };
+    assert_no_failures(
+        [
+            check_golden("shadow_eclipse", &render_shadowed("earth_like_planet_shader", moon)),
+            check_golden("shadow_ring", &render_shadowed("gas_giant_shader", ring)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+    );
+}
+
+#[test]
+fn near_plane_clipping_matches_golden_image() {
+    // The near plane cuts the front cap of the sphere open
+    let framebuffer = render_perspective(Vec3::new(0.0, 0.0, 2.0), 1.2, "earth_like_planet_shader", CullMode::None);
+    assert_no_failures(check_golden("clip_near_plane", &framebuffer).into_iter().collect());
+}
+
+#[test]
+fn camera_inside_sphere_matches_golden_image() {
+    // Half of the sphere is behind the camera (w < 0) and must be clipped, not smeared
+    let framebuffer = render_perspective(Vec3::new(0.0, 0.0, 0.4), 0.1, "earth_like_planet_shader", CullMode::None);
+    assert_no_failures(check_golden("clip_inside_sphere", &framebuffer).into_iter().collect());
+}
+
+#[test]
+fn perspective_correct_uvs_match_golden_image() {
+    // Close-up of the checkerboard: screen-space interpolation would bend the squares
+    let framebuffer = render_perspective(Vec3::new(0.0, 0.6, 1.6), 0.1, "uv_checker_shader", CullMode::Back);
+    assert_no_failures(check_golden("perspective_uv_checker", &framebuffer).into_iter().collect());
+}
+
+#[test]
+fn skybox_streaks_match_golden_image() {
+    let camera = Camera::new(Vec3::zeros(), glm::quat_identity(), SIZE, SIZE);
+    let mut uniforms = scene_uniforms(Mat4::identity(), CullMode::None);
+    uniforms.view_matrix = camera.view_matrix();
+    uniforms.camera_position = camera.position;
+    uniforms.projection_matrix = camera.projection_matrix();
+    uniforms.viewport_matrix = camera.viewport_matrix();
+
+    // Flying forward and a little to the right: the stars stream out from ahead of the camera
+    let skybox = Skybox::with_seed(3000, 11);
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.clear();
+    skybox.render(&mut framebuffer, &uniforms, camera.position, Vec3::new(0.2, 0.0, -1.0).normalize() * 0.6);
+    framebuffer.resolve();
+    assert_no_failures(check_golden("skybox_streaks", &framebuffer).into_iter().collect());
+}
+
+#[test]
+fn parallel_rasterizer_matches_serial_path() {
+    let (width, height) = (200, 150);
+    let scene = Scene::load(
+        Vec3::new(width as f32 / 2.0, height as f32 / 2.0, 0.0),
+        Skybox::with_seed(500, 7),
+    );
+    let camera = initial_camera(scene.center, width, height);
+
+    let render_with_threads = |threads: usize, anti_aliasing: AntiAliasing| {
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.set_thread_count(threads);
+        framebuffer.set_anti_aliasing(anti_aliasing);
+        render_frame(&mut framebuffer, &scene, &camera, TIME, STAR, None);
+        framebuffer
+    };
+
+    for anti_aliasing in [AntiAliasing::Off, AntiAliasing::Msaa4x] {
+        let serial = render_with_threads(1, anti_aliasing);
+        let parallel = render_with_threads(8, anti_aliasing);
+        assert!(serial.buffer == parallel.buffer, "parallel color buffer differs from the serial path ({:?})", anti_aliasing);
+        assert!(serial.zbuffer == parallel.zbuffer, "parallel depth buffer differs from the serial path ({:?})", anti_aliasing);
+    }
+}
+
+#[test]
+fn early_z_and_depth_prepass_do_not_change_the_image() {
+    let obj = Obj::load(&format!("{}/assets/jet.obj", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let vertex_array = obj.get_vertex_array();
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let uniforms = scene_uniforms(
+        create_model_matrix(center, 4.0, Vec3::new(-1.0, 0.0, 0.0)),
+        CullMode::None,
+    );
+
+    let draw = |early_z: bool, depth_prepass: bool| {
+        let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+        framebuffer.clear();
+        framebuffer.set_early_z(early_z);
+        if depth_prepass {
+            framebuffer.set_depth_pass(DepthPass::DepthOnly);
+            render(&mut framebuffer, &uniforms, &vertex_array, "jet_shader");
+            framebuffer.set_depth_pass(DepthPass::Shading);
+        }
+        render(&mut framebuffer, &uniforms, &vertex_array, "jet_shader");
+        framebuffer.resolve();
+        framebuffer
+    };
+
+    let early = draw(true, false);
+    let late = draw(false, false);
+    let prepass = draw(true, true);
+
+    assert!(early.buffer == late.buffer, "early-z changed the image");
+    assert!(early.buffer == prepass.buffer, "depth pre-pass changed the image");
+    assert!(early.stats.shaded < late.stats.shaded, "early-z did not skip any shading");
+    assert_eq!(prepass.stats.shaded, prepass.covered_pixels() as u64);
+}
+
+#[test]
+fn textured_sphere_matches_golden_image() {
+    let mut texture = Texture::load(format!("{}/assets/textures/mars.png", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    texture.set_wrap(WrapMode::Repeat, WrapMode::ClampToEdge);
+
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let mut uniforms = scene_uniforms(create_model_matrix(center, 40.0, Vec3::new(0.3, 0.0, 0.5)), CullMode::Back);
+    uniforms.texture = Some(Arc::new(texture));
+
+    uniforms.texture_blend = 1.0;
+    let textured = render_with("spheresmooth.obj", "rocky_planet", &uniforms);
+    uniforms.texture_blend = 0.5;
+    let blended = render_with("spheresmooth.obj", "rocky_planet", &uniforms);
+
+    // Far away the texture is minified and must come from the smaller mip levels
+    uniforms.model_matrix = create_model_matrix(center, 8.0, Vec3::new(0.3, 0.0, 0.5));
+    uniforms.normal_matrix = uniforms.model_matrix.try_inverse().unwrap().transpose();
+    uniforms.texture_blend = 1.0;
+    let far = render_with("spheresmooth.obj", "rocky_planet", &uniforms);
+
+    let failures = [
+        check_golden("sphere_textured", &textured),
+        check_golden("sphere_textured_blend", &blended),
+        check_golden("sphere_textured_far", &far),
+    ];
+    assert_no_failures(failures.into_iter().flatten().collect());
+}
+
+#[test]
+fn texture_keeps_its_top_half_on_top() {
+    // Only the top half of the image is red, so a flipped V would show up upside down
+    let image = RgbImage::from_fn(8, 8, |_, y| if y < 4 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) });
+    let mut texture = Texture::from_image(&image);
+    texture.set_wrap(WrapMode::Repeat, WrapMode::ClampToEdge);
+
+    let center = Vec3::new(SIZE as f32 / 2.0, SIZE as f32 / 2.0, 0.0);
+    let mut uniforms = scene_uniforms(create_model_matrix(center, 40.0, Vec3::zeros()), CullMode::Back);
+    uniforms.texture = Some(Arc::new(texture));
+    uniforms.texture_blend = 1.0;
+    let framebuffer = render_with("spheresmooth.obj", "rocky_planet", &uniforms);
+
+    let image = framebuffer.to_image();
+    let (top, bottom) = (image.get_pixel(48, 24), image.get_pixel(48, 72));
+    assert!(top[0] > top[2], "top of the sphere is not red: {:?}", top);
+    assert!(bottom[2] > bottom[0], "bottom of the sphere is not blue: {:?}", bottom);
+    assert_no_failures(check_golden("texture_orientation", &framebuffer).into_iter().collect());
+}
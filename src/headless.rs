@@ -0,0 +1,238 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use nalgebra_glm::Vec3;
+use crate::framebuffer::{AntiAliasing, DebugView, Framebuffer, ToneMapping};
+use crate::postprocess::PostChain;
+use crate::skybox::Skybox;
+use crate::{initial_camera, render_frame, Scene, STAR};
+
+const USAGE: &str = "Usage: Space-Travel --headless [--frames N] [--output DIR] [--size WxH] [--time T] [--seed S] [--threads N] [--no-early-z] [--depth-prepass] [--aa off|ssaa|msaa] [--tone-map clamp|reinhard|aces] [--exposure EV] [--no-bloom] [--post none|EFFECT,...] [--debug-view off|wireframe|normals|depth|overdraw|objects] [--stats] [--benchmark]";
+
+pub struct HeadlessOptions {
+    pub frames: u32,
+    pub output_dir: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub start_time: u32,
+    pub seed: u64,
+    pub threads: Option<usize>,
+    pub early_z: bool,
+    pub depth_prepass: bool,
+    pub anti_aliasing: AntiAliasing,
+    pub tone_mapping: ToneMapping,
+    pub exposure: f32,
+    pub bloom: bool,
+    // Post-processing effects to enable, by name; None keeps the chain's defaults
+    pub post_effects: Option<Vec<String>>,
+    pub debug_view: DebugView,
+    pub stats: bool,
+    pub benchmark: bool,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        HeadlessOptions {
+            frames: 1,
+            output_dir: PathBuf::from("frames"),
+            width: 800,
+            height: 600,
+            start_time: 0,
+            seed: 42,
+            threads: None,
+            early_z: true,
+            depth_prepass: false,
+            anti_aliasing: AntiAliasing::Off,
+            tone_mapping: ToneMapping::Aces,
+            exposure: 0.0,
+            bloom: true,
+            post_effects: None,
+            debug_view: DebugView::Off,
+            stats: false,
+            benchmark: false,
+        }
+    }
+}
+
+impl HeadlessOptions {
+    // Returns None when `--headless` was not requested
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        if !args.iter().any(|arg| arg == "--headless") {
+            return Ok(None);
+        }
+
+        let mut options = HeadlessOptions::default();
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("Missing value for {}\n{}", name, USAGE))
+            };
+
+            match arg.as_str() {
+                "--headless" => {}
+                "--no-early-z" => options.early_z = false,
+                "--depth-prepass" => options.depth_prepass = true,
+                "--no-bloom" => options.bloom = false,
+                "--stats" => options.stats = true,
+                "--benchmark" => options.benchmark = true,
+                "--frames" => options.frames = parse_number(&value("--frames")?)?,
+                "--output" => options.output_dir = PathBuf::from(value("--output")?),
+                "--time" => options.start_time = parse_number(&value("--time")?)?,
+                "--seed" => options.seed = parse_number(&value("--seed")?)?,
+                "--threads" => options.threads = Some(parse_number(&value("--threads")?)?),
+                "--aa" => {
+                    options.anti_aliasing = match value("--aa")?.as_str() {
+                        "off" => AntiAliasing::Off,
+                        "ssaa" => AntiAliasing::Ssaa4x,
+                        "msaa" => AntiAliasing::Msaa4x,
+                        other => return Err(format!("Invalid anti-aliasing mode '{}'\n{}", other, USAGE)),
+                    }
+                }
+                "--tone-map" => {
+                    options.tone_mapping = match value("--tone-map")?.as_str() {
+                        "clamp" => ToneMapping::Clamp,
+                        "reinhard" => ToneMapping::Reinhard,
+                        "aces" => ToneMapping::Aces,
+                        other => return Err(format!("Invalid tone mapping '{}'\n{}", other, USAGE)),
+                    }
+                }
+                "--post" => {
+                    let list = value("--post")?;
+                    let names: Vec<String> = if list == "none" {
+                        Vec::new()
+                    } else {
+                        list.split(',').map(str::to_string).collect()
+                    };
+                    let known: Vec<_> = PostChain::standard().names().collect();
+                    if let Some(other) = names.iter().find(|name| !known.contains(&name.as_str())) {
+                        return Err(format!("Unknown post effect '{}', expected one of {}\n{}", other, known.join(","), USAGE));
+                    }
+                    options.post_effects = Some(names);
+                }
+                "--debug-view" => {
+                    options.debug_view = match value("--debug-view")?.as_str() {
+                        "off" => DebugView::Off,
+                        "wireframe" => DebugView::Wireframe,
+                        "normals" => DebugView::Normals,
+                        "depth" => DebugView::Depth,
+                        "overdraw" => DebugView::Overdraw,
+                        "objects" => DebugView::Objects,
+                        other => return Err(format!("Invalid debug view '{}'\n{}", other, USAGE)),
+                    }
+                }
+                "--exposure" => options.exposure = parse_number(&value("--exposure")?)?,
+                "--size" => {
+                    let size = value("--size")?;
+                    let (width, height) = size
+                        .split_once('x')
+                        .ok_or_else(|| format!("Invalid size '{}', expected WxH", size))?;
+                    options.width = parse_number(width)?;
+                    options.height = parse_number(height)?;
+                }
+                other => return Err(format!("Unknown argument '{}'\n{}", other, USAGE)),
+            }
+        }
+
+        if options.width == 0 || options.height == 0 {
+            return Err(format!("Invalid size {}x{}", options.width, options.height));
+        }
+
+        Ok(Some(options))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid number '{}'", value))
+}
+
+// Renders `frames` consecutive frames from the initial camera and writes them as PNG files.
+// In benchmark mode nothing is written and only the frame times are reported.
+pub fn run(options: &HeadlessOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if !options.benchmark {
+        fs::create_dir_all(&options.output_dir)?;
+    }
+
+    let mut framebuffer = Framebuffer::new(options.width, options.height);
+    if let Some(threads) = options.threads {
+        framebuffer.set_thread_count(threads);
+    }
+    framebuffer.set_early_z(options.early_z);
+    framebuffer.set_depth_prepass(options.depth_prepass);
+    framebuffer.set_anti_aliasing(options.anti_aliasing);
+    framebuffer.set_tone_mapping(options.tone_mapping);
+    framebuffer.set_exposure(options.exposure);
+    framebuffer.set_bloom(options.bloom);
+    let mut post_chain = PostChain::standard();
+    if let Some(names) = &options.post_effects {
+        let enabled: Vec<bool> = post_chain.names().map(|name| names.iter().any(|wanted| wanted == name)).collect();
+        for (index, enabled) in enabled.into_iter().enumerate() {
+            post_chain.set_enabled(index, enabled);
+        }
+    }
+    framebuffer.set_post_chain(post_chain);
+    framebuffer.set_debug_view(options.debug_view);
+    let scene = Scene::load(
+        Vec3::new(options.width as f32 / 2.0, options.height as f32 / 2.0, 0.0),
+        Skybox::with_seed(10000, options.seed),
+    );
+
+    let camera = initial_camera(scene.center, options.width, options.height);
+
+    let mut frame_times = Vec::with_capacity(options.frames as usize);
+
+    for frame in 0..options.frames {
+        let time = options.start_time + frame;
+
+        let start = Instant::now();
+        render_frame(&mut framebuffer, &scene, &camera, time, STAR, None);
+        frame_times.push(start.elapsed());
+
+        if !options.benchmark {
+            let path = options.output_dir.join(format!("frame_{:04}.png", frame));
+            framebuffer.save_png(&path)?;
+            println!("Wrote {}", path.display());
+        }
+
+        if options.stats {
+            let stats = framebuffer.stats;
+            println!(
+                "  fragments {} | shaded {} | written {} | covered pixels {} | overdraw {:.2}",
+                stats.fragments,
+                stats.shaded,
+                stats.written,
+                framebuffer.covered_pixels(),
+                framebuffer.overdraw()
+            );
+        }
+    }
+
+    if options.benchmark {
+        report_frame_times(&frame_times);
+    }
+
+    Ok(())
+}
+
+fn report_frame_times(frame_times: &[Duration]) {
+    if frame_times.is_empty() {
+        return;
+    }
+
+    let mut sorted = frame_times.to_vec();
+    sorted.sort();
+    let total: Duration = sorted.iter().sum();
+    let average = total / sorted.len() as u32;
+    let to_ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+
+    println!(
+        "{} frames: average {:.2} ms ({:.1} FPS) | median {:.2} ms | min {:.2} ms | max {:.2} ms",
+        sorted.len(),
+        to_ms(average),
+        1.0 / average.as_secs_f64(),
+        to_ms(sorted[sorted.len() / 2]),
+        to_ms(sorted[0]),
+        to_ms(sorted[sorted.len() - 1])
+    );
+}
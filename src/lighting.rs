@@ -0,0 +1,388 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+
+// Light in world space. There is no distance falloff: the sun is the only light and the outer
+// planets would be invisible with an inverse-square law at these distances.
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub position: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    // Radius of the light's sphere, which sets how wide the penumbra of shadows is
+    // (0.0 for a point light with hard shadows)
+    pub radius: f32,
+}
+
+impl Light {
+    pub fn new(position: Vec3, color: Color, intensity: f32) -> Self {
+        Light { position, color, intensity, radius: 0.0 }
+    }
+
+    pub fn with_radius(self, radius: f32) -> Self {
+        Light { radius, ..self }
+    }
+
+    // Unit vector from `point` towards the light
+    pub fn direction_from(&self, point: &Vec3) -> Vec3 {
+        let direction = self.position - point;
+        let length = direction.norm();
+        if length > 0.0 { direction / length } else { Vec3::zeros() }
+    }
+
+    // Lambert term for a surface at `point` with unit `normal`, scaled by the intensity
+    pub fn diffuse(&self, point: &Vec3, normal: &Vec3) -> f32 {
+        normal.dot(&self.direction_from(point)).max(0.0) * self.intensity
+    }
+}
+
+// Something that can block the light, in world space
+#[derive(Clone, Copy, Debug)]
+pub enum Occluder {
+    Sphere { center: Vec3, radius: f32 },
+    // Flat annulus around `center`, perpendicular to the unit `normal`, that lets through
+    // `1.0 - opacity` of the light
+    Ring { center: Vec3, normal: Vec3, inner_radius: f32, outer_radius: f32, opacity: f32 },
+}
+
+// Shadows cast by the bodies of the scene, computed analytically: the light is a sphere and
+// each occluder covers part of it as seen from the shaded point, which gives soft penumbras
+// without rendering a shadow map.
+#[derive(Clone, Debug, Default)]
+pub struct Shadows {
+    occluders: Vec<Occluder>,
+}
+
+impl Shadows {
+    pub fn new() -> Self {
+        Shadows::default()
+    }
+
+    pub fn add(&mut self, occluder: Occluder) {
+        self.occluders.push(occluder);
+    }
+
+    // Fraction of `light` that reaches `point`, from 0.0 (umbra) to 1.0 (fully lit). A sphere
+    // doesn't shadow points on its own surface: its night side is already dark.
+    pub fn visibility(&self, light: &Light, point: &Vec3) -> f32 {
+        if self.occluders.is_empty() {
+            return 1.0;
+        }
+        let to_light = light.position - point;
+        let distance = to_light.norm();
+        if distance <= light.radius {
+            return 1.0;
+        }
+        let view = LightView { direction: to_light / distance, distance, size: (light.radius / distance).asin() };
+
+        let mut visibility = 1.0;
+        for occluder in &self.occluders {
+            visibility *= match *occluder {
+                Occluder::Sphere { center, radius } => sphere_visibility(point, &view, &center, radius),
+                Occluder::Ring { center, normal, inner_radius, outer_radius, opacity } => {
+                    1.0 - opacity * ring_coverage(point, &view, &center, &normal, inner_radius, outer_radius)
+                }
+            };
+            if visibility <= 0.0 {
+                return 0.0;
+            }
+        }
+        visibility
+    }
+}
+
+// Occluder spheres have the radius of the mesh's vertices, and fragments interpolated across
+// its triangles can land slightly off it, so points within 2% of it count as its own surface
+const SELF_SHADOW_MARGIN: f32 = 1.02;
+
+// The light seen from a shaded point: unit direction, distance and angular radius
+struct LightView {
+    direction: Vec3,
+    distance: f32,
+    size: f32,
+}
+
+// Part of the light's disc left uncovered by a sphere, treating both as discs on the sky
+fn sphere_visibility(point: &Vec3, light: &LightView, center: &Vec3, radius: f32) -> f32 {
+    let offset = center - point;
+    let along = offset.dot(&light.direction);
+    // Behind the point, beyond the light, or the surface the point is on
+    if along <= 0.0 || along >= light.distance || offset.norm_squared() <= (radius * SELF_SHADOW_MARGIN).powi(2) {
+        return 1.0;
+    }
+    let distance = offset.norm();
+    let occluder_size = (radius / distance).asin();
+    let separation = (along / distance).clamp(-1.0, 1.0).acos();
+    if separation >= light.size + occluder_size {
+        return 1.0;
+    }
+    if light.size <= 0.0 {
+        return 0.0;
+    }
+    1.0 - disc_overlap(light.size, occluder_size, separation) / (std::f32::consts::PI * light.size * light.size)
+}
+
+// Area shared by two discs of radius `a` and `b` whose centers are `distance` apart
+fn disc_overlap(a: f32, b: f32, distance: f32) -> f32 {
+    if distance >= a + b {
+        return 0.0;
+    }
+    if distance <= (a - b).abs() {
+        return std::f32::consts::PI * a.min(b).powi(2);
+    }
+    let (a2, b2, d2) = (a * a, b * b, distance * distance);
+    let lens = (-distance + a + b) * (distance + a - b) * (distance - a + b) * (distance + a + b);
+    a2 * ((d2 + a2 - b2) / (2.0 * distance * a)).clamp(-1.0, 1.0).acos()
+        + b2 * ((d2 + b2 - a2) / (2.0 * distance * b)).clamp(-1.0, 1.0).acos()
+        - 0.5 * lens.max(0.0).sqrt()
+}
+
+// How much of the light a ring covers: where the ray towards the light crosses the ring's
+// plane, blurred across the edges by the size of the light seen from there
+fn ring_coverage(point: &Vec3, light: &LightView, center: &Vec3, normal: &Vec3, inner_radius: f32, outer_radius: f32) -> f32 {
+    let facing = light.direction.dot(normal);
+    if facing.abs() < 1e-4 {
+        return 0.0;
+    }
+    let distance = (center - point).dot(normal) / facing;
+    // Points on the ring itself don't shadow themselves
+    if distance <= outer_radius * 1e-3 || distance >= light.distance {
+        return 0.0;
+    }
+    let crossing = (point + light.direction * distance - center).norm();
+    let blur = (distance * light.size.tan()).max(1e-4);
+    let edge = |inside: f32| (inside / blur * 0.5 + 0.5).clamp(0.0, 1.0);
+    edge(crossing - inner_radius) * edge(outer_radius - crossing)
+}
+
+// Reflectance of a dielectric (non-metal) seen head-on
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+
+// How the light reflects off a surface
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LightingModel {
+    // Lambert diffuse plus a Blinn-Phong highlight, from `specular` and `shininess`
+    #[default]
+    BlinnPhong,
+    // Metallic-roughness model: Lambert diffuse and a GGX highlight with Fresnel, from
+    // `metallic` and `roughness`. Energy conserving: a surface never reflects more light than
+    // it receives.
+    Pbr,
+}
+
+// How a surface responds to the light, for either lighting model. The albedo comes from the
+// shader, so the same material works for every fragment of a procedural body.
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceMaterial {
+    // Light the surface reflects no matter where the light is, so the night side isn't black
+    pub ambient: Color,
+    // Color and exponent of the Blinn-Phong highlight; black for surfaces without one
+    pub specular: Color,
+    pub shininess: f32,
+    // 0.0 for dielectrics, 1.0 for metals (which tint their reflections with the albedo)
+    pub metallic: f32,
+    // 0.0 is a perfect mirror, 1.0 completely diffuse
+    pub roughness: f32,
+    // Light the surface gives off on its own
+    pub emissive: Color,
+}
+
+impl SurfaceMaterial {
+    // Only diffuse light plus an ambient term
+    pub const fn matte(ambient: Color) -> Self {
+        SurfaceMaterial {
+            ambient,
+            specular: Color::black(),
+            shininess: 1.0,
+            metallic: 0.0,
+            roughness: 1.0,
+            emissive: Color::black(),
+        }
+    }
+
+    // Color of a surface with `albedo` at `point`, with unit `normal`, seen from the unit
+    // `view_direction` (pointing from the surface towards the viewer)
+    pub fn shade(
+        &self,
+        model: LightingModel,
+        albedo: Color,
+        light: &Light,
+        point: &Vec3,
+        normal: &Vec3,
+        view_direction: &Vec3,
+    ) -> Color {
+        let light_direction = light.direction_from(point);
+        let color = self.ambient + self.emissive;
+        if normal.dot(&light_direction) <= 0.0 {
+            return color;
+        }
+
+        let reflected = match model {
+            LightingModel::BlinnPhong => self.blinn_phong(albedo, &light_direction, normal, view_direction),
+            LightingModel::Pbr => self.physically_based(albedo, &light_direction, normal, view_direction),
+        };
+        color + reflected.blend_multiply(&light.color) * light.intensity
+    }
+
+    // The light reflected towards the viewer by both models is relative to a light of intensity
+    // 1.0 shining head-on, which a white diffuse surface facing it reflects as 1.0
+    fn blinn_phong(&self, albedo: Color, light_direction: &Vec3, normal: &Vec3, view_direction: &Vec3) -> Color {
+        let diffuse = albedo * normal.dot(light_direction);
+        if self.specular.is_black() {
+            return diffuse;
+        }
+        let half_vector = (light_direction + view_direction).normalize();
+        let highlight = normal.dot(&half_vector).max(0.0).powf(self.shininess);
+        diffuse + self.specular * highlight
+    }
+
+    // Cook-Torrance BRDF times the cosine of the light, scaled by pi so a white Lambert
+    // surface matches the Blinn-Phong diffuse term
+    fn physically_based(&self, albedo: Color, light_direction: &Vec3, normal: &Vec3, view_direction: &Vec3) -> Color {
+        let n_dot_l = normal.dot(light_direction);
+        let n_dot_v = normal.dot(view_direction).max(1e-4);
+        let half_vector = (light_direction + view_direction).normalize();
+        let n_dot_h = normal.dot(&half_vector).max(0.0);
+        let v_dot_h = view_direction.dot(&half_vector).max(0.0);
+
+        // GGX distribution of the microfacet normals
+        let roughness = self.roughness.clamp(0.045, 1.0);
+        let alpha_squared = roughness.powi(4);
+        let denominator = n_dot_h * n_dot_h * (alpha_squared - 1.0) + 1.0;
+        let distribution = alpha_squared / (std::f32::consts::PI * denominator * denominator);
+
+        // Smith-Schlick masking and shadowing, with the k used for analytic lights
+        let k = (roughness + 1.0).powi(2) / 8.0;
+        let masking = |cosine: f32| cosine / (cosine * (1.0 - k) + k);
+        let geometry = masking(n_dot_l) * masking(n_dot_v);
+
+        // Schlick's Fresnel: metals reflect with their albedo, dielectrics with a dim white
+        let metallic = self.metallic.clamp(0.0, 1.0);
+        let fresnel_weight = (1.0 - v_dot_h).powi(5);
+        let specular = distribution * geometry / (4.0 * n_dot_l * n_dot_v) * std::f32::consts::PI;
+        let channel = |albedo: f32| {
+            let reflectance = DIELECTRIC_REFLECTANCE + (albedo - DIELECTRIC_REFLECTANCE) * metallic;
+            let fresnel = reflectance + (1.0 - reflectance) * fresnel_weight;
+            // Light that isn't reflected by the surface is what's left for the diffuse term
+            ((1.0 - fresnel) * (1.0 - metallic) * albedo + fresnel * specular) * n_dot_l
+        };
+        Color::from_f32(channel(albedo.r()), channel(albedo.g()), channel(albedo.b()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blinn_phong_material_terms() {
+        let material = SurfaceMaterial {
+            ambient: Color::from_f32(0.05, 0.05, 0.05),
+            specular: Color::from_f32(0.5, 0.5, 0.5),
+            shininess: 32.0,
+            metallic: 0.0,
+            roughness: 1.0,
+            emissive: Color::from_f32(0.0, 0.0, 0.1),
+        };
+        let light = Light::new(Vec3::new(0.0, 0.0, -100.0), Color::from_f32(1.0, 1.0, 1.0), 1.0);
+        let albedo = Color::from_f32(0.4, 0.2, 0.0);
+        let view = Vec3::new(0.0, 0.0, -1.0);
+        let shade = |normal: Vec3| material.shade(LightingModel::BlinnPhong, albedo, &light, &Vec3::zeros(), &normal.normalize(), &view);
+        let rgb = |color: Color| (color.r(), color.g(), color.b());
+        let close = |a: (f32, f32, f32), b: (f32, f32, f32)| (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5 && (a.2 - b.2).abs() < 1e-5;
+
+        // Facing the light and the viewer: ambient + emissive + full diffuse + full highlight
+        let lit = rgb(shade(Vec3::new(0.0, 0.0, -1.0)));
+        assert!(close(lit, (0.95, 0.75, 0.65)), "{:?}", lit);
+        // Facing away: only the ambient and emissive terms are left
+        let night = rgb(shade(Vec3::new(0.0, 0.0, 1.0)));
+        assert!(close(night, (0.05, 0.05, 0.15)), "{:?}", night);
+        // Off the mirror direction the highlight falls off much faster than the diffuse term
+        let grazing = shade(Vec3::new(1.0, 0.0, -1.0));
+        let highlight = grazing.b() - 0.15;
+        assert!(highlight > 0.0 && highlight < 0.01, "{}", highlight);
+    }
+
+    #[test]
+    fn pbr_material_conserves_energy() {
+        let light = Light::new(Vec3::new(0.0, 0.0, -100.0), Color::from_f32(1.0, 1.0, 1.0), 1.0);
+        let normal = Vec3::new(0.0, 0.0, -1.0);
+        let white = Color::from_f32(1.0, 1.0, 1.0);
+
+        // Light reflected over the whole hemisphere, relative to the light the surface receives
+        let reflected = |metallic: f32, roughness: f32| {
+            let material = SurfaceMaterial { metallic, roughness, ..SurfaceMaterial::matte(Color::black()) };
+            let (rings, segments) = (256, 64);
+            let mut total = 0.0;
+            for ring in 0..rings {
+                let theta = (ring as f32 + 0.5) / rings as f32 * std::f32::consts::FRAC_PI_2;
+                for segment in 0..segments {
+                    let phi = (segment as f32 + 0.5) / segments as f32 * std::f32::consts::TAU;
+                    let view = Vec3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), -theta.cos());
+                    let radiance = material.shade(LightingModel::Pbr, white, &light, &Vec3::zeros(), &normal, &view).g();
+                    let solid_angle = theta.sin() * (std::f32::consts::FRAC_PI_2 / rings as f32) * (std::f32::consts::TAU / segments as f32);
+                    total += radiance * theta.cos() * solid_angle;
+                }
+            }
+            total / std::f32::consts::PI
+        };
+
+        for (metallic, roughness) in [(0.0, 1.0), (0.0, 0.5), (0.0, 0.3), (1.0, 0.5), (1.0, 0.3)] {
+            let energy = reflected(metallic, roughness);
+            assert!(energy <= 1.0 + 1e-3, "metallic {} roughness {}: {}", metallic, roughness, energy);
+            assert!(energy > 0.75, "metallic {} roughness {}: {}", metallic, roughness, energy);
+        }
+
+        // Facing the light, a white rough dielectric is as bright as the Blinn-Phong diffuse term
+        // apart from what its Fresnel reflection takes away
+        let matte = SurfaceMaterial::matte(Color::black());
+        let shade = |model: LightingModel| matte.shade(model, white, &light, &Vec3::zeros(), &normal, &normal).g();
+        let (pbr, blinn_phong) = (shade(LightingModel::Pbr), shade(LightingModel::BlinnPhong));
+        assert!((pbr - blinn_phong).abs() < 0.1, "pbr {} blinn-phong {}", pbr, blinn_phong);
+    }
+
+    #[test]
+    fn shadows_cover_the_light() {
+        let light = Light::new(Vec3::new(0.0, 0.0, -1000.0), Color::from_f32(1.0, 1.0, 1.0), 1.0).with_radius(10.0);
+        let mut shadows = Shadows::new();
+        shadows.add(Occluder::Sphere { center: Vec3::new(0.0, 0.0, -100.0), radius: 5.0 });
+
+        // Umbra behind the occluder, a penumbra that brightens outwards and full light beyond it
+        assert_eq!(shadows.visibility(&light, &Vec3::zeros()), 0.0);
+        let penumbra: Vec<f32> = (40..=60).map(|x| shadows.visibility(&light, &Vec3::new(x as f32 * 0.1, 0.0, 0.0))).collect();
+        assert!(penumbra.windows(2).all(|pair| pair[0] <= pair[1] + 1e-6), "{:?}", penumbra);
+        assert!(penumbra.iter().any(|&visibility| visibility > 0.1 && visibility < 0.9), "{:?}", penumbra);
+        assert_eq!(shadows.visibility(&light, &Vec3::new(10.0, 0.0, 0.0)), 1.0);
+        // A sphere doesn't shadow its own surface, and nothing behind the point casts a shadow
+        assert_eq!(shadows.visibility(&light, &Vec3::new(0.0, 0.0, -95.0)), 1.0);
+        assert_eq!(shadows.visibility(&light, &Vec3::new(0.0, 0.0, -1100.0)), 1.0);
+
+        // A ring only shadows the points whose light goes through the ring itself
+        let mut ring = Shadows::new();
+        ring.add(Occluder::Ring {
+            center: Vec3::new(0.0, 0.0, -100.0),
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            inner_radius: 10.0,
+            outer_radius: 20.0,
+            opacity: 0.6,
+        });
+        assert_eq!(ring.visibility(&light, &Vec3::zeros()), 1.0);
+        assert!((ring.visibility(&light, &Vec3::new(16.0, 0.0, 0.0)) - 0.4).abs() < 1e-4);
+        assert_eq!(ring.visibility(&light, &Vec3::new(30.0, 0.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn sphere_does_not_shadow_its_own_lit_surface() {
+        let light = Light::new(Vec3::new(0.0, 0.0, -1000.0), Color::from_f32(1.0, 1.0, 1.0), 1.0).with_radius(10.0);
+        let center = Vec3::new(0.0, 0.0, -100.0);
+        let mut shadows = Shadows::new();
+        shadows.add(Occluder::Sphere { center, radius: 5.0 });
+
+        // Just past the terminator, where a triangle that straddles it is still lit through its
+        // interpolated normal, and rounded a hair outside the radius: the sphere would hide the
+        // light from there, but the point is on its own surface
+        let angle = 91.0_f32.to_radians();
+        let point = center + Vec3::new(angle.sin(), 0.0, -angle.cos()) * 5.0 * 1.0005;
+        assert!((center - point).dot(&light.direction_from(&point)) > 0.0);
+        assert_eq!(shadows.visibility(&light, &point), 1.0);
+    }
+}
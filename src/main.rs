@@ -1,6 +1,6 @@
-use nalgebra_glm::{Vec2, Vec3, Mat4};
-use minifb::{Key, Window, WindowOptions};
-use std::time::Duration;
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat4, Quat};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use std::time::{Duration, Instant};
 
 mod framebuffer;
 mod triangle;
@@ -11,51 +11,62 @@ mod color;
 mod fragment;
 mod shaders;
 mod skybox;
-
-use framebuffer::Framebuffer;
+mod clipping;
+mod rasterizer;
+mod headless;
+mod texture;
+mod bloom;
+mod postprocess;
+mod lighting;
+mod camera;
+#[cfg(test)]
+mod golden_tests;
+
+use framebuffer::{BlendMode, DebugView, DepthPass, Framebuffer};
 use vertex::Vertex;
-use obj::Obj;
-use triangle::triangle;
+use obj::{MaterialMesh, Obj, Material};
+use triangle::{is_culled, wireframe_triangle, CullMode};
 use shaders::vertex_shader;
+use fragment::{CORONA_SCALE, HIGHLIGHT_SCALE};
 use color::Color;
-use crate::fragment::fragment_shader;
+use crate::clipping::clip_triangle;
+use crate::rasterizer::rasterize;
 use fastnoise_lite::{FastNoiseLite, NoiseType, CellularDistanceFunction};
 use std::clone::Clone;
 use nalgebra_glm as glm;
-use crate::line::draw_line;
+use crate::headless::HeadlessOptions;
+use crate::skybox::Skybox;
+use crate::camera::{is_perspective, Camera, Pose, Ray, Warp};
+use crate::lighting::{Light, LightingModel, Occluder, Shadows};
+use crate::postprocess::PostChain;
+use crate::texture::{Texture, WrapMode};
+use std::sync::Arc;
 
 
 
+#[derive(Clone)]
 pub struct Uniforms {
     model_matrix: Mat4,
     view_matrix: Mat4,
+    camera_position: Vec3,
     projection_matrix: Mat4,
     viewport_matrix: Mat4,
     normal_matrix: Mat4, 
     time: u32,
-    noise_open_simplex: FastNoiseLite,
-    noise_cellular: FastNoiseLite,
-    noise_perlin: FastNoiseLite,
-    noise_value: FastNoiseLite,
-    noise_value_cubic: FastNoiseLite,
-}
-
-impl Clone for Uniforms {
-    fn clone(&self) -> Self {
-        Uniforms {
-            model_matrix: self.model_matrix,
-            view_matrix: self.view_matrix,
-            projection_matrix: self.projection_matrix,
-            viewport_matrix: self.viewport_matrix,
-            normal_matrix: self.normal_matrix, 
-            time: self.time,
-            noise_open_simplex: create_open_simplex_noise(),
-            noise_cellular: create_cellular_noise(),
-            noise_perlin: create_perlin_noise(),
-            noise_value: create_value_noise(),
-            noise_value_cubic: create_value_cubic_noise(),
-        }
-    }
+    // FastNoiseLite no implementa Clone: los dibujos de un cuadro comparten los mismos ruidos
+    noise_open_simplex: Arc<FastNoiseLite>,
+    noise_cellular: Arc<FastNoiseLite>,
+    noise_perlin: Arc<FastNoiseLite>,
+    noise_value: Arc<FastNoiseLite>,
+    noise_value_cubic: Arc<FastNoiseLite>,
+    cull_mode: CullMode,
+    frustum_clip: bool,
+    texture: Option<Arc<Texture>>,
+    texture_blend: f32,
+    material: Option<Material>,
+    light: Light,
+    lighting_model: LightingModel,
+    shadows: Arc<Shadows>,
 }
 
 fn create_cellular_noise() -> FastNoiseLite {
@@ -106,64 +117,69 @@ fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
     let translation_matrix = glm::translation(&translation);
 
     // Combinar las transformaciones: primero escala, luego rotación y finalmente traslación
-    let model_matrix = translation_matrix * rotation_matrix * scaling_matrix;
-
-    model_matrix
+    translation_matrix * rotation_matrix * scaling_matrix
 }
 
-fn create_view_matrix(translation: Vec3, rotation: Vec3, scale: f32) -> Mat4 {
-    // Invertimos las transformaciones para la cámara
-    let translation_matrix = glm::translation(&-translation);
-    let scaling_matrix = glm::scaling(&Vec3::new(1.0 / scale, 1.0 / scale, 1.0 / scale));
-
-    let rotation_matrix_x = glm::rotation(-rotation.x, &Vec3::x_axis());
-    let rotation_matrix_y = glm::rotation(-rotation.y, &Vec3::y_axis());
-    let rotation_matrix_z = glm::rotation(-rotation.z, &Vec3::z_axis());
-
-    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
-
-    let view_matrix = scaling_matrix * rotation_matrix * translation_matrix;
-
-    view_matrix
-}
-
-
 fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], shader_type: &str) {
-    // Transformar vértices usando el vertex shader
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
-    }
+    // Se reutilizan los buffers de la llamada anterior para no reservar memoria en cada cuadro
+    let mut scratch = std::mem::take(&mut framebuffer.scratch);
 
-    // Triangulación de los vértices
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+    // Transformar vértices usando el vertex shader
+    scratch.vertices.clear();
+    scratch.vertices.extend(vertex_array.iter().map(|vertex| vertex_shader(vertex, uniforms)));
+
+    // Triangulación por índices, recorte contra el volumen de vista y descarte de caras traseras
+    scratch.triangles.clear();
+    for i in (0..vertex_array.len() / 3).map(|t| t * 3) {
+        scratch.clipped.clear();
+        clip_triangle(
+            [i, i + 1, i + 2],
+            &mut scratch.vertices,
+            uniforms.frustum_clip,
+            &uniforms.viewport_matrix,
+            &mut scratch.clip,
+            &mut scratch.clipped,
+        );
+        for &[i1, i2, i3] in &scratch.clipped {
+            let vertices = &scratch.vertices;
+            if !is_culled(&vertices[i1], &vertices[i2], &vertices[i3], uniforms.cull_mode) {
+                scratch.triangles.push([i1, i2, i3]);
+            }
         }
     }
 
-    // Rasterización de los triángulos
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
+    // Rasterización por tiles y fragment shader en paralelo
+    rasterize(framebuffer, &mut scratch, uniforms, shader_type);
+    if framebuffer.debug_view() == DebugView::Wireframe && framebuffer.depth_pass() != DepthPass::DepthOnly {
+        draw_wireframe(framebuffer, &scratch.vertices, &scratch.triangles, is_perspective(&uniforms.projection_matrix));
     }
+    framebuffer.scratch = scratch;
+}
 
-    // Aplicar el fragment shader a cada fragmento
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            let shaded_color = fragment_shader(&fragment, uniforms, shader_type);
-            framebuffer.set_current_color(shaded_color.to_hex());
-            framebuffer.point(x, y, fragment.depth);
+const WIREFRAME_COLOR: Color = Color::new(80, 255, 120);
+
+// Aristas de los triángulos visibles, un poco por delante de la superficie para que la prueba
+// de profundidad no las esconda detrás de su propio triángulo. Con perspectiva la profundidad
+// se acerca a 1.0 con la distancia, así que el margen se mide respecto a ese límite
+fn draw_wireframe(framebuffer: &mut Framebuffer, vertices: &[Vertex], triangles: &[[usize; 3]], perspective: bool) {
+    let (blend_mode, depth_write) = (framebuffer.blend_mode(), framebuffer.depth_write());
+    framebuffer.set_blend_mode(BlendMode::Opaque);
+    framebuffer.set_depth_write(false);
+    framebuffer.set_current_color(WIREFRAME_COLOR.to_hex());
+
+    for &[i1, i2, i3] in triangles {
+        for fragment in wireframe_triangle(&vertices[i1], &vertices[i2], &vertices[i3]) {
+            let (x, y) = (fragment.position.x, fragment.position.y);
+            if x >= 0.0 && y >= 0.0 {
+                let bias = if perspective { 1.0 - fragment.depth } else { 1.0 + fragment.depth.abs() };
+                let depth = fragment.depth - 0.01 * bias;
+                framebuffer.point(x as usize, y as usize, depth);
+            }
         }
     }
+
+    framebuffer.set_blend_mode(blend_mode);
+    framebuffer.set_depth_write(depth_write);
 }
 
 
@@ -180,23 +196,13 @@ fn create_orbit_points(center: Vec3, radius: f32, segments: usize) -> Vec<Vertex
             color: Color::new(255, 255, 255), 
             transformed_position: Vec3::zeros(),
             transformed_normal: Vec3::zeros(),
+            world_position: Vec3::new(x, y, 0.0),
+            clip_position: Vec4::new(x, y, 0.0, 1.0),
         });
     }
     points
 }
 
-fn render_orbit(
-    framebuffer: &mut Framebuffer,
-    points: &[Vertex],
-    color: Color,
-) {
-    for i in 0..points.len() {
-        let p1 = &points[i];
-        let p2 = &points[(i + 1) % points.len()]; 
-        draw_line(p1, p2, framebuffer, color);
-    }
-}
-
 pub struct Planet {
     name: &'static str,
     scale: f32,
@@ -208,14 +214,49 @@ pub struct Planet {
     ring_scale: Option<f32>,          
     moon_shader: Option<&'static str>, 
     moon_scale: Option<f32>,         
+    atmosphere_shader: Option<&'static str>,
     zoom_level: f32,                  
+    // Textura opcional y cuánto reemplaza al shader procedural (1.0 = solo textura)
+    texture: Option<&'static str>,
+    texture_blend: f32,
+    // Modelo de iluminación del planeta y de su luna
+    lighting_model: LightingModel,
 }
 
 //WARPS
-pub struct WarpPoint {
-    name: &'static str,
-    position: Vec3,
-    zoom_level: f32, 
+// Cuerpo que sigue la cámara: un planeta, su luna o su anillo, por el índice del planeta en la
+// escena
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Focus {
+    Planet(usize),
+    Moon(usize),
+    Ring(usize),
+}
+
+impl Focus {
+    fn planet(self) -> usize {
+        match self {
+            Focus::Planet(index) | Focus::Moon(index) | Focus::Ring(index) => index,
+        }
+    }
+}
+
+// La cámara vuela libre, viaja hacia un cuerpo (warp) o lo sigue a cierta distancia, girando a
+// su alrededor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    Free,
+    Warp { focus: Focus, distance: f32, warp: Warp },
+    Follow { focus: Focus, distance: f32 },
+}
+
+impl CameraMode {
+    pub fn focus(&self) -> Option<Focus> {
+        match *self {
+            CameraMode::Free => None,
+            CameraMode::Warp { focus, .. } | CameraMode::Follow { focus, .. } => Some(focus),
+        }
+    }
 }
 
 fn calculate_planet_position(center: Vec3, orbit_radius: f32, orbit_speed: f32, time: u32) -> Vec3 {
@@ -225,29 +266,173 @@ fn calculate_planet_position(center: Vec3, orbit_radius: f32, orbit_speed: f32,
     Vec3::new(x, y, center.z) 
 }
 
-fn create_warp_points(planets: &[Planet], sun_position: Vec3, time: u32) -> Vec<WarpPoint> {
-    planets
-        .iter()
-        .map(|planet| WarpPoint {
-            name: planet.name,
-            position: calculate_planet_position(sun_position, planet.orbit_radius, planet.orbit_speed, time),
-            zoom_level: planet.zoom_level, 
-        })
-        .collect()
+// La luna gira alrededor de su planeta a una distancia proporcional al tamaño del planeta
+fn calculate_moon_position(planet: &Planet, planet_position: Vec3, time: u32) -> Vec3 {
+    let moon_orbit_radius = planet.scale * 100.0;
+    let moon_angle = time as f32 * 0.01;
+    planet_position + Vec3::new(moon_orbit_radius * moon_angle.cos(), moon_orbit_radius * moon_angle.sin(), 0.0)
 }
 
+// Posición y radio del cuerpo en el cuadro `time`, los mismos con los que se dibuja (para un
+// anillo, su radio exterior). Un planeta sin luna o sin anillo se representa a sí mismo.
+fn focus_body(scene: &Scene, focus: Focus, time: u32) -> (Vec3, f32) {
+    let planet = &scene.planets[focus.planet()];
+    let planet_position = calculate_planet_position(scene.center, planet.orbit_radius, planet.orbit_speed, time);
+    match (focus, planet.moon_scale, planet.ring_scale) {
+        (Focus::Moon(_), Some(moon_scale), _) => {
+            (calculate_moon_position(planet, planet_position, time), scene.moon_radius * moon_scale * 10.0)
+        }
+        (Focus::Ring(_), _, Some(ring_scale)) => (planet_position, scene.ring_radii.1 * ring_scale * 10.0),
+        _ => (planet_position, planet.scale * 10.0),
+    }
+}
 
-fn main() {
+// Cuerpo más cercano que cruza el rayo: planetas, lunas y anillos en su posición del cuadro `time`
+fn pick_body(scene: &Scene, ray: &Ray, time: u32) -> Option<Focus> {
+    let mut nearest: Option<(f32, Focus)> = None;
+    let mut hit = |distance: Option<f32>, focus: Focus| {
+        if let Some(distance) = distance {
+            let closer = match nearest {
+                Some((nearest_distance, _)) => distance < nearest_distance,
+                None => true,
+            };
+            if closer {
+                nearest = Some((distance, focus));
+            }
+        }
+    };
 
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
-    let frame_delay = Duration::from_millis(16);
+    for (i, planet) in scene.planets.iter().enumerate() {
+        let (position, radius) = focus_body(scene, Focus::Planet(i), time);
+        hit(ray.sphere(&position, radius), Focus::Planet(i));
 
+        // El anillo está en el plano xz del modelo y se dibuja sin rotación
+        if let Some(ring_scale) = planet.ring_scale {
+            let (inner_radius, outer_radius) = scene.ring_radii;
+            let distance = ray.ring(&position, &Vec3::y(), inner_radius * ring_scale * 10.0, outer_radius * ring_scale * 10.0);
+            hit(distance, Focus::Ring(i));
+        }
+        if planet.moon_scale.is_some() {
+            let (position, radius) = focus_body(scene, Focus::Moon(i), time);
+            hit(ray.sphere(&position, radius), Focus::Moon(i));
+        }
+    }
+    nearest.map(|(_, focus)| focus)
+}
+
+
+// Identificadores de los cuerpos celestes que se pueden seleccionar
+const STAR: u8 = 1;
+const VOLCANIC_PLANET: u8 = 3;
+const CRYSTAL: u8 = 6;
+const VORTEX: u8 = 7;
+const RINGED_PLANET: u8 = 10;
+const ROCKY_PLANET: u8 = 11;
+const EARTH_LIKE_PLANET: u8 = 12;
+
+// Todo lo que se necesita para dibujar un cuadro del sistema solar
+pub struct Scene {
+    planets: Vec<Planet>,
+    planet_textures: Vec<Option<Arc<Texture>>>,
+    skybox: Skybox,
+    center: Vec3,
+    orbits: Vec<Vec<Vertex>>,
+    sphere_vertex_array: Vec<Vertex>,
+    ring_vertex_array: Vec<Vertex>,
+    moon_vertex_array: Vec<Vertex>,
+    // Radio de la malla de la luna y radios interior y exterior del anillo, para las sombras
+    moon_radius: f32,
+    ring_radii: (f32, f32),
+    jet_meshes: Vec<MaterialMesh>,
+    jet_textures: Vec<Option<Arc<Texture>>>,
+}
+
+impl Scene {
+    pub fn load(center: Vec3, skybox: Skybox) -> Self {
+        let planets = create_planets();
+
+        let obj = Obj::load("assets/spheresmooth.obj").expect("Failed to load obj");
+        let ring_obj = Obj::load("assets/ring.obj").expect("Failed to load rings.obj");
+        let moon_obj = Obj::load("assets/moon.obj").expect("Failed to load moon.obj");
+        let jet_obj = Obj::load("assets/jet.obj").expect("Failed to load jet.obj");
+
+        // Las texturas envuelven horizontalmente y se fijan en los polos
+        let planet_textures = planets
+            .iter()
+            .map(|planet| {
+                planet.texture.map(|path| {
+                    let mut texture = Texture::load(path)
+                        .unwrap_or_else(|err| panic!("Failed to load {}: {}", path, err));
+                    texture.set_wrap(WrapMode::Repeat, WrapMode::ClampToEdge);
+                    Arc::new(texture)
+                })
+            })
+            .collect();
+
+        //Orbitas
+        let orbit_segments = 60; 
+        let orbits = planets
+            .iter()
+            .map(|planet| create_orbit_points(center, planet.orbit_radius, orbit_segments))
+            .collect();
+
+        // Mallas de la nave con los materiales de jet.mtl (y su textura difusa, si tienen)
+        let jet_meshes = jet_obj.get_material_meshes();
+        let jet_textures = jet_meshes
+            .iter()
+            .map(|mesh| {
+                mesh.material.diffuse_texture.as_ref().map(|path| {
+                    let texture = Texture::load(path).unwrap_or_else(|err| {
+                        panic!("Failed to load {} for material {}: {}", path.display(), mesh.material.name, err)
+                    });
+                    Arc::new(texture)
+                })
+            })
+            .collect();
+
+        let ring_vertex_array = ring_obj.get_vertex_array();
+        let moon_vertex_array = moon_obj.get_vertex_array();
+
+        Scene {
+            planets,
+            planet_textures,
+            skybox,
+            center,
+            orbits,
+            sphere_vertex_array: obj.get_vertex_array(),
+            moon_radius: mesh_radii(&moon_vertex_array).1,
+            ring_radii: mesh_radii(&ring_vertex_array),
+            ring_vertex_array,
+            moon_vertex_array,
+            jet_meshes,
+            jet_textures,
+        }
+    }
+}
+
+// Distancia mínima y máxima de los vértices de una malla a su origen
+fn mesh_radii(vertices: &[Vertex]) -> (f32, f32) {
+    vertices.iter().fold((f32::MAX, 0.0_f32), |(inner, outer), vertex| {
+        let radius = vertex.position.norm();
+        (inner.min(radius), outer.max(radius))
+    })
+}
+
+// Cámara inicial: inclinada sobre el plano de las órbitas y lo bastante lejos del Sol para ver
+// el sistema completo
+fn initial_camera(center: Vec3, width: usize, height: usize) -> Camera {
+    let mut camera = Camera::new(Vec3::zeros(), camera_tilt(INITIAL_CAMERA_TILT), width, height);
+    camera.frame(center, INITIAL_CAMERA_DISTANCE);
+    camera
+}
 
-    // Define el arreglo de planetas dentro de `main`
-    let planets = vec![
+// Orientación de una cámara inclinada `degrees` grados sobre el plano de las órbitas
+fn camera_tilt(degrees: f32) -> Quat {
+    glm::quat_angle_axis(degrees.to_radians(), &Vec3::x())
+}
+
+fn create_planets() -> Vec<Planet> {
+    vec![
         Planet {
             name: "Mercury",
             scale: 4.0,
@@ -259,7 +444,11 @@ fn main() {
             ring_scale: None,
             moon_shader: None,
             moon_scale: None,
+            atmosphere_shader: None,
             zoom_level: 1.5,
+            texture: None,
+            texture_blend: 0.0,
+            lighting_model: LightingModel::BlinnPhong,
         },
         Planet {
             name: "Venus",
@@ -272,7 +461,11 @@ fn main() {
             ring_scale: None,
             moon_shader: None,
             moon_scale: None,
-            zoom_level: 1.5, 
+            atmosphere_shader: None,
+            zoom_level: 1.5,
+            texture: None,
+            texture_blend: 0.0,
+            lighting_model: LightingModel::BlinnPhong,
         },
         Planet {
             name: "Earth",
@@ -285,7 +478,11 @@ fn main() {
             ring_scale: None,
             moon_shader: Some("moon_shader"),
             moon_scale: Some(6.0),
-            zoom_level: 1.5, 
+            atmosphere_shader: Some("atmosphere_shader"),
+            zoom_level: 1.5,
+            texture: None,
+            texture_blend: 0.0,
+            lighting_model: LightingModel::Pbr,
         },
         Planet {
             name: "Mars",
@@ -298,7 +495,11 @@ fn main() {
             ring_scale: None,
             moon_shader: None,
             moon_scale: None,
+            atmosphere_shader: None,
             zoom_level: 1.5,
+            texture: Some("assets/textures/mars.png"),
+            texture_blend: 0.75,
+            lighting_model: LightingModel::BlinnPhong,
         },
         Planet {
             name: "Jupiter",
@@ -311,7 +512,11 @@ fn main() {
             ring_scale: None,
             moon_shader: None,
             moon_scale: None,
-            zoom_level: 2.0, 
+            atmosphere_shader: None,
+            zoom_level: 2.0,
+            texture: None,
+            texture_blend: 0.0,
+            lighting_model: LightingModel::BlinnPhong,
         },
         Planet {
             name: "Saturn",
@@ -324,7 +529,11 @@ fn main() {
             ring_scale: Some(10.0),
             moon_shader: Some("moon_shader"),
             moon_scale: Some(10.0),
-            zoom_level: 2.0, 
+            atmosphere_shader: None,
+            zoom_level: 2.0,
+            texture: None,
+            texture_blend: 0.0,
+            lighting_model: LightingModel::BlinnPhong,
         },
         Planet {
             name: "Uranus",
@@ -337,525 +546,652 @@ fn main() {
             ring_scale: None,
             moon_shader: None,
             moon_scale: None,
-            zoom_level: 1.8, 
+            atmosphere_shader: None,
+            zoom_level: 1.8,
+            texture: None,
+            texture_blend: 0.0,
+            lighting_model: LightingModel::BlinnPhong,
         },
-    ];
-
-
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
-    let mut window = Window::new(
-        "Solar System",
-        window_width,
-        window_height,
-        WindowOptions::default(),
-    )
-    .unwrap();
-
-    window.set_position(500, 500);
-    window.update();
-
-    //SKYBOX
-    let skybox = skybox::Skybox::new(10000); // Ajusta el número de estrellas
-
-
-    let obj = Obj::load("assets/spheresmooth.obj").expect("Failed to load obj");
-    let vertex_arrays = obj.get_vertex_array();
-    let ring_obj = Obj::load("assets/ring.obj").expect("Failed to load rings.obj");
-    let ring_vertex_array = ring_obj.get_vertex_array();
-    let moon_obj = Obj::load("assets/moon.obj").expect("Failed to load moon.obj");
-    let moon_vertex_array = moon_obj.get_vertex_array();
-    let jet_obj = Obj::load("assets/jet.obj").expect("Failed to load jet.obj");
-    let jet_vertex_array = jet_obj.get_vertex_array();
-
-    let mut time = 0;
-
-    // Añadimos las constantes para identificar los cuerpos celestes
-    const STAR: u8 = 1;
-    const VOLCANIC_PLANET: u8 = 3;
-    const CRYSTAL: u8 = 6;
-    const VORTEX: u8 = 7;
-    const RINGED_PLANET: u8 = 10;
-    const ROCKY_PLANET: u8 = 11;
-    const EARTH_LIKE_PLANET: u8 = 12;
-    // Variable para guardar el cuerpo celeste seleccionado
-    let mut selected_object: u8 = STAR;
-
-    // Definir las variables de la cámara al inicio de `main`
-    let mut camera_translation = Vec3::new(-500.0, 0.0, -1000.0); // Cámara más alejada
-    let mut camera_rotation = Vec3::new(1.0, 0.5, 0.0);
-    let mut camera_scale = 5.0f32;
-
-
-    //Orbitas
-    let orbit_segments = 60; 
-    let orbits: Vec<Vec<Vertex>> = planets
-        .iter()
-        .map(|planet| create_orbit_points(
-            Vec3::new(window_width as f32 / 2.0, window_height as f32 / 2.0, 0.0),
-            planet.orbit_radius,
-            orbit_segments,
-        ))
-        .collect();
-
-
-
+    ]
+}
 
+// Dibujo translúcido pendiente; se hace después de toda la geometría opaca
+struct TransparentDraw<'a> {
+    depth: f32,
+    uniforms: Uniforms,
+    vertex_array: &'a [Vertex],
+    shader_type: &'static str,
+    blend_mode: BlendMode,
+}
 
-    while window.is_open() {
-        if window.is_key_down(Key::Escape) {
-            break;
+impl<'a> TransparentDraw<'a> {
+    fn new(uniforms: Uniforms, vertex_array: &'a [Vertex], shader_type: &'static str, blend_mode: BlendMode) -> Self {
+        // Profundidad en pantalla del origen del modelo, para ordenar los dibujos
+        let center = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        TransparentDraw {
+            depth: center.z / center.w,
+            uniforms,
+            vertex_array,
+            shader_type,
+            blend_mode,
         }
+    }
+}
 
-        time += 1;
-
-        framebuffer.clear();
+// Geometría translúcida de atrás hacia adelante, con prueba de profundidad pero sin escribirla
+fn render_transparent(framebuffer: &mut Framebuffer, draws: &mut [TransparentDraw]) {
+    draws.sort_by(|a, b| b.depth.total_cmp(&a.depth));
 
-        //SUN POSITION
-        let sun_position = Vec3::new(
-            window_width as f32 / 2.0,
-            window_height as f32 / 2.0,
-            camera_translation.z, 
-        );
+    framebuffer.set_depth_write(false);
+    for draw in draws.iter() {
+        framebuffer.set_blend_mode(draw.blend_mode);
+        render(framebuffer, &draw.uniforms, draw.vertex_array, draw.shader_type);
+    }
+    framebuffer.set_blend_mode(BlendMode::Opaque);
+    framebuffer.set_depth_write(true);
+}
 
+// Limpia el framebuffer y dibuja un cuadro completo, con pre-pase de profundidad si está activo
+fn render_frame(
+    framebuffer: &mut Framebuffer,
+    scene: &Scene,
+    camera: &Camera,
+    time: u32,
+    selected_object: u8,
+    highlight: Option<Focus>,
+) {
+    framebuffer.clear();
+    let mut transparent = Vec::new();
+
+    if framebuffer.depth_prepass() {
+        framebuffer.set_depth_pass(DepthPass::DepthOnly);
+        render_scene(framebuffer, scene, camera, time, selected_object, highlight, &mut transparent);
+        transparent.clear();
+        framebuffer.set_depth_pass(DepthPass::Shading);
+    }
 
+    render_scene(framebuffer, scene, camera, time, selected_object, highlight, &mut transparent);
+    framebuffer.set_depth_pass(DepthPass::Standard);
 
-        let warp_points = create_warp_points(&planets, sun_position, time);    
+    // Lo translúcido no participa del pre-pase: se prueba contra la profundidad de lo opaco
+    render_transparent(framebuffer, &mut transparent);
 
-        handle_warp(&window, &warp_points, &mut camera_translation, &mut camera_rotation, &mut camera_scale);
+    // Promediar las muestras (con anti-aliasing) y aplicar el mapeo de tonos al buffer final
+    framebuffer.resolve();
+}
 
-        handle_input(&window, &mut camera_translation, &mut camera_rotation, &mut camera_scale);
+// Color e intensidad de la luz del Sol sobre los planetas
+const SUN_LIGHT_COLOR: Color = Color::from_f32(1.0, 0.96, 0.9);
+const SUN_LIGHT_INTENSITY: f32 = 1.1;
+// Radio del Sol (la esfera del modelo tiene radio 1)
+const SUN_SCALE: f32 = 200.0;
+// Fracción de la luz que tapa el anillo en promedio (sus bandas son translúcidas)
+const RING_SHADOW_OPACITY: f32 = 0.6;
+// Inclinación en grados de la cámara inicial sobre el plano de las órbitas, y su distancia al Sol
+const INITIAL_CAMERA_TILT: f32 = 120.0;
+const INITIAL_CAMERA_DISTANCE: f32 = 6000.0;
+// Inclinación de la cámara al seguir a un cuerpo: mira el sistema desde arriba
+const FOLLOW_CAMERA_TILT: f32 = 180.0;
+// Fracción de la distancia al cuerpo seguido que se acerca o aleja la cámara por cuadro, y
+// distancia mínima en radios del cuerpo
+const FOLLOW_DOLLY_SPEED: f32 = 0.03;
+const FOLLOW_MIN_RADII: f32 = 2.0;
+
+// Cuerpos que pueden tapar la luz del Sol en este cuadro: los planetas, sus lunas y anillos.
+// Los objetos seleccionados se dibujan dentro del Sol, así que no proyectan sombra.
+fn create_shadows(scene: &Scene, time: u32) -> Shadows {
+    let mut shadows = Shadows::new();
+    for planet in &scene.planets {
+        let position = calculate_planet_position(scene.center, planet.orbit_radius, planet.orbit_speed, time);
+        shadows.add(Occluder::Sphere { center: position, radius: planet.scale * 10.0 });
+
+        // El anillo está en el plano xz del modelo y se dibuja sin rotación
+        if let Some(ring_scale) = planet.ring_scale {
+            let (inner_radius, outer_radius) = scene.ring_radii;
+            shadows.add(Occluder::Ring {
+                center: position,
+                normal: Vec3::y(),
+                inner_radius: inner_radius * ring_scale * 10.0,
+                outer_radius: outer_radius * ring_scale * 10.0,
+                opacity: RING_SHADOW_OPACITY,
+            });
+        }
+        if let Some(moon_scale) = planet.moon_scale {
+            shadows.add(Occluder::Sphere {
+                center: calculate_moon_position(planet, position, time),
+                radius: scene.moon_radius * moon_scale * 10.0,
+            });
+        }
+    }
+    shadows
+}
 
-        let view_matrix = create_view_matrix(camera_translation, camera_rotation, camera_scale);
+// Dibuja la geometría opaca y deja en `transparent` los dibujos translúcidos
+fn render_scene<'a>(
+    framebuffer: &mut Framebuffer,
+    scene: &'a Scene,
+    camera: &Camera,
+    time: u32,
+    selected_object: u8,
+    highlight: Option<Focus>,
+    transparent: &mut Vec<TransparentDraw<'a>>,
+) {
+    // Todos los dibujos comparten las matrices de la cámara
+    let (view_matrix, projection_matrix, viewport_matrix) =
+        (camera.view_matrix(), camera.projection_matrix(), camera.viewport_matrix());
+    let camera_position = camera.position;
+
+    // El Sol es la única luz: todo se ilumina desde su posición, y su tamaño da la penumbra
+    // de las sombras y los eclipses
+    let sun_light = Light::new(scene.center, SUN_LIGHT_COLOR, SUN_LIGHT_INTENSITY).with_radius(SUN_SCALE);
+    let shadows = Arc::new(create_shadows(scene, time));
+
+    // Lo que comparten todos los dibujos del cuadro: la cámara, la luz, las sombras y los ruidos.
+    // Cada dibujo parte de aquí y cambia solo lo suyo
+    let base = Uniforms {
+        model_matrix: Mat4::identity(),
+        view_matrix,
+        camera_position,
+        projection_matrix,
+        viewport_matrix,
+        normal_matrix: Mat4::identity(),
+        time,
+        noise_open_simplex: Arc::new(create_open_simplex_noise()),
+        noise_cellular: Arc::new(create_cellular_noise()),
+        noise_perlin: Arc::new(create_perlin_noise()),
+        noise_value: Arc::new(create_value_noise()),
+        noise_value_cubic: Arc::new(create_value_cubic_noise()),
+        cull_mode: CullMode::Back,
+        frustum_clip: true,
+        texture: None,
+        texture_blend: 0.0,
+        material: None,
+        light: sun_light,
+        lighting_model: LightingModel::BlinnPhong,
+        shadows,
+    };
+
+    // Renderizar el Skybox
+    scene.skybox.render(framebuffer, &base, camera.position, camera.streak);
+
+    let orbit_uniforms = Uniforms { cull_mode: CullMode::None, ..base.clone() };
+    for orbit_points in &scene.orbits {
+        render(framebuffer, &orbit_uniforms, orbit_points, "orbit_shader");
+    }
 
-        // Crear uniforms para el Skybox
-        let skybox_uniforms = Uniforms {
-            model_matrix: Mat4::identity(),
-            view_matrix,
-            projection_matrix: glm::perspective(
-                framebuffer_width as f32 / framebuffer_height as f32, 
-                45.0_f32.to_radians(),                               
-                0.1,                                                 
-                2000.0,                                              
-            ),
-            viewport_matrix: glm::scaling(&Vec3::new(
-                framebuffer_width as f32 / 2.0,
-                framebuffer_height as f32 / 2.0,
-                1.0,
-            )),
-            normal_matrix: Mat4::identity(),
-            time,
-            noise_open_simplex: create_open_simplex_noise(),
-            noise_cellular: create_cellular_noise(),
-            noise_perlin: create_perlin_noise(),
-            noise_value: create_value_noise(),
-            noise_value_cubic: create_value_cubic_noise(),
+    // Renderizar el Sol
+    let sun_translation = scene.center;
+    let sun_rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05); 
+    let sun_scale = SUN_SCALE;
+
+    let sun_model_matrix = create_model_matrix(sun_translation, sun_scale, sun_rotation);
+    let normal_matrix = sun_model_matrix.try_inverse().unwrap().transpose();
+
+
+    let sun_uniforms = Uniforms {
+        normal_matrix,
+        model_matrix: sun_model_matrix,
+        ..base.clone()
+    };
+
+    render(
+        framebuffer,
+        &sun_uniforms,
+        &scene.sphere_vertex_array,
+        "solar_surface",
+    );
+
+    // Corona: esfera mayor que el Sol sumada con mezcla aditiva
+    let corona_model_matrix = create_model_matrix(sun_translation, sun_scale * CORONA_SCALE, sun_rotation);
+    let corona_uniforms = Uniforms {
+        model_matrix: corona_model_matrix,
+        normal_matrix: corona_model_matrix.try_inverse().unwrap().transpose(),
+        ..sun_uniforms.clone()
+    };
+    transparent.push(TransparentDraw::new(corona_uniforms, &scene.sphere_vertex_array, "corona_shader", BlendMode::Additive));
+
+    // Aro alrededor del cuerpo que sigue la cámara, elegido con el ratón o con las teclas
+    if let Some(focus) = highlight {
+        let (position, radius) = focus_body(scene, focus, time);
+        let highlight_model_matrix = create_model_matrix(position, radius * HIGHLIGHT_SCALE, Vec3::zeros());
+        let highlight_uniforms = Uniforms {
+            model_matrix: highlight_model_matrix,
+            normal_matrix: highlight_model_matrix.try_inverse().unwrap().transpose(),
+            ..sun_uniforms.clone()
         };
-        
-
-        // Cambiamos el objeto seleccionado con teclas
-        if window.is_key_down(Key::Key1) {
-            selected_object = STAR;
-        } else if window.is_key_down(Key::Key2) {
-            selected_object = VOLCANIC_PLANET;
-        } else if window.is_key_down(Key::Key3) {
-            selected_object = CRYSTAL;
-        } else if window.is_key_down(Key::Key4) {
-            selected_object = VORTEX;
-        } else if window.is_key_down(Key::Key5) {
-            selected_object = RINGED_PLANET;
-        } else if window.is_key_down(Key::Key6) {
-            selected_object = ROCKY_PLANET;
-        } else if window.is_key_down(Key::Key7) {
-            selected_object = EARTH_LIKE_PLANET;
-        }
-        
-
-        // Renderizar el Skybox
-        skybox.render(&mut framebuffer, &skybox_uniforms, camera_translation);
-
-        for orbit_points in &orbits {
-            let orbit_model_matrix = Mat4::identity(); 
-            let orbit_uniforms = Uniforms {
-                model_matrix: orbit_model_matrix,
-                view_matrix: view_matrix,
-                projection_matrix: Mat4::identity(),
-                viewport_matrix: Mat4::identity(),
-                normal_matrix: orbit_model_matrix.try_inverse().unwrap().transpose(),
-                time,
-                noise_open_simplex: create_open_simplex_noise(),
-                noise_cellular: create_cellular_noise(),
-                noise_perlin: create_perlin_noise(),
-                noise_value: create_value_noise(),
-                noise_value_cubic: create_value_cubic_noise(),
-            };
-        
-            render(&mut framebuffer, &orbit_uniforms, &orbit_points, "orbit_shader");
-        }
+        transparent.push(TransparentDraw::new(
+            highlight_uniforms,
+            &scene.sphere_vertex_array,
+            "highlight_shader",
+            BlendMode::Additive,
+        ));
+    }
 
-        // Renderizar el Sol
-        let sun_translation =
-            Vec3::new(window_width as f32 / 2.0, window_height as f32 / 2.0, 0.0);
-        let sun_rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05); 
-        let sun_scale = 200.0; 
 
-        let sun_model_matrix = create_model_matrix(sun_translation, sun_scale, sun_rotation);
-        let normal_matrix = sun_model_matrix.try_inverse().unwrap().transpose();
+    // Renderizar los planetas
+    for (planet, texture) in scene.planets.iter().zip(&scene.planet_textures) {
+        let planet_position = calculate_planet_position(scene.center, planet.orbit_radius, planet.orbit_speed, time);
+        let (orbit_x, orbit_y) = (planet_position.x, planet_position.y);
 
+        let model_matrix = create_model_matrix(
+            Vec3::new(orbit_x, orbit_y, 0.0),
+            planet.scale * 10.0, 
+            Vec3::new(0.0, 0.0, time as f32 * planet.rotation_speed),
+        );
 
-        let sun_uniforms = Uniforms {
+        let normal_matrix = model_matrix.try_inverse().unwrap().transpose();
+        let planet_uniforms = Uniforms {
             normal_matrix,
-            model_matrix: sun_model_matrix,
-            view_matrix: view_matrix,
-            projection_matrix: Mat4::identity(),
-            viewport_matrix: Mat4::identity(),
-            time,
-            noise_open_simplex: create_open_simplex_noise(),
-            noise_cellular: create_cellular_noise(),
-            noise_perlin: create_perlin_noise(),
-            noise_value: create_value_noise(),
-            noise_value_cubic: create_value_cubic_noise(),
+            model_matrix,
+            texture: texture.clone(),
+            texture_blend: planet.texture_blend,
+            lighting_model: planet.lighting_model,
+            ..base.clone()
         };
 
         render(
-            &mut framebuffer,
-            &sun_uniforms,
-            &vertex_arrays,
-            "solar_surface",
+            framebuffer,
+            &planet_uniforms,
+            &scene.sphere_vertex_array,
+            planet.shader
         );
 
-
-        // Renderizar los planetas
-        for planet in &planets {
-            let orbit_points = create_orbit_points(
-                Vec3::new(window_width as f32 / 2.0, window_height as f32 / 2.0, 0.0),
-                planet.orbit_radius,
-                100, 
+        if let (Some(ring_shader), Some(ring_scale)) = (planet.ring_shader, planet.ring_scale) {
+            let ring_model_matrix = create_model_matrix(
+                Vec3::new(orbit_x, orbit_y, 0.0), 
+                ring_scale * 10.0,               
+                Vec3::new(0.0, 0.0, 0.0),        
             );
+            
+        
+            let ring_normal_matrix = ring_model_matrix.try_inverse().unwrap().transpose();
+        
+            // El anillo es un plano de una sola cara: se dibuja por ambos lados
+            let ring_uniforms = Uniforms {
+                normal_matrix: ring_normal_matrix,
+                model_matrix: ring_model_matrix,
+                cull_mode: CullMode::None,
+                ..base.clone()
+            };
+        
+            transparent.push(TransparentDraw::new(ring_uniforms, &scene.ring_vertex_array, ring_shader, BlendMode::Alpha));
+        }
 
-            render_orbit(&mut framebuffer, &orbit_points, Color::new(255, 255, 255)); 
-
-
-            let angle = time as f32 * planet.orbit_speed;
-            let orbit_x = (planet.orbit_radius * angle.cos()) + (window_width as f32 / 2.0);
-            let orbit_y = (planet.orbit_radius * angle.sin()) + (window_height as f32 / 2.0);
-
-            let model_matrix = create_model_matrix(
+        // Atmósfera: capa algo mayor que el planeta, con alfa premultiplicado
+        if let Some(atmosphere_shader) = planet.atmosphere_shader {
+            let atmosphere_model_matrix = create_model_matrix(
                 Vec3::new(orbit_x, orbit_y, 0.0),
-                planet.scale * 10.0, 
-                Vec3::new(0.0, 0.0, time as f32 * planet.rotation_speed),
+                planet.scale * 10.0 * 1.08,
+                Vec3::new(0.0, 0.0, 0.0),
             );
+            let atmosphere_uniforms = Uniforms {
+                model_matrix: atmosphere_model_matrix,
+                normal_matrix: atmosphere_model_matrix.try_inverse().unwrap().transpose(),
+                texture: None,
+                ..planet_uniforms.clone()
+            };
+            transparent.push(TransparentDraw::new(
+                atmosphere_uniforms,
+                &scene.sphere_vertex_array,
+                atmosphere_shader,
+                BlendMode::Premultiplied,
+            ));
+        }
 
-            let normal_matrix = model_matrix.try_inverse().unwrap().transpose();
-            let planet_uniforms = Uniforms {
-                normal_matrix,
+        if let (Some(moon_shader), Some(moon_scale)) = (planet.moon_shader, planet.moon_scale) {
+            let moon_position = calculate_moon_position(planet, planet_position, time);
+        
+            let moon_model_matrix = create_model_matrix(
+                moon_position,
+                moon_scale * 10.0,
+                Vec3::new(0.0, 0.0, 0.0),
+            );
+        
+            let moon_normal_matrix = moon_model_matrix.try_inverse().unwrap().transpose();
+        
+            let moon_uniforms = Uniforms {
+                normal_matrix: moon_normal_matrix,
+                model_matrix: moon_model_matrix,
+                lighting_model: planet.lighting_model,
+                ..base.clone()
+            };
+        
+            render(framebuffer, &moon_uniforms, &scene.moon_vertex_array, moon_shader);
+        }
+        
+        
+    }
+    
+    // Renderizar el objeto seleccionado con shaders específicos
+    match selected_object {
+        VOLCANIC_PLANET => {
+            let translation = scene.center;
+            let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05);
+            let scale = 30.0;
+            let model_matrix = create_model_matrix(translation, scale, rotation);
+
+            let uniforms = Uniforms {
                 model_matrix,
-                view_matrix: view_matrix,
-                projection_matrix: Mat4::identity(),
-                viewport_matrix: Mat4::identity(),
-                time,
-                noise_open_simplex: create_open_simplex_noise(),
-                noise_cellular: create_cellular_noise(),
-                noise_perlin: create_perlin_noise(),
-                noise_value: create_value_noise(),
-                noise_value_cubic: create_value_cubic_noise(),
+                normal_matrix,
+                ..base.clone()
             };
 
+            framebuffer.set_current_color(0xFF4500);
             render(
-                &mut framebuffer,
-                &planet_uniforms,
-                &vertex_arrays,
-                planet.shader
+                framebuffer,
+                &uniforms,
+                &scene.sphere_vertex_array,
+                "volcanic_planet_shader",
             );
+        }
+        CRYSTAL => {
+            let translation = scene.center;
+            let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05);
+            let scale = 30.0;
+            let model_matrix = create_model_matrix(translation, scale, rotation);
 
-            if let (Some(ring_shader), Some(ring_scale)) = (planet.ring_shader, planet.ring_scale) {
-                let ring_model_matrix = create_model_matrix(
-                    Vec3::new(orbit_x, orbit_y, 0.0), 
-                    ring_scale * 10.0,               
-                    Vec3::new(0.0, 0.0, 0.0),        
-                );
-                
-            
-                let ring_normal_matrix = ring_model_matrix.try_inverse().unwrap().transpose();
-            
-                let ring_uniforms = Uniforms {
-                    normal_matrix: ring_normal_matrix,
-                    model_matrix: ring_model_matrix,
-                    view_matrix: view_matrix,
-                    projection_matrix: Mat4::identity(),
-                    viewport_matrix: Mat4::identity(),
-                    time,
-                    noise_open_simplex: create_open_simplex_noise(),
-                    noise_cellular: create_cellular_noise(),
-                    noise_perlin: create_perlin_noise(),
-                    noise_value: create_value_noise(),
-                    noise_value_cubic: create_value_cubic_noise(),
-                };
-            
-                render(&mut framebuffer, &ring_uniforms, &ring_vertex_array, ring_shader);
-            }
+            let uniforms = Uniforms {
+                model_matrix,
+                normal_matrix,
+                ..base.clone()
+            };
 
-            if let (Some(moon_shader), Some(moon_scale)) = (planet.moon_shader, planet.moon_scale) {
-                let moon_orbit_radius = planet.scale * 100.0; // Relación con el tamaño del planeta
-                let moon_angle = time as f32 * 0.01;         // Ajusta la velocidad angular
-                let moon_x = orbit_x + moon_orbit_radius * moon_angle.cos();
-                let moon_y = orbit_y + moon_orbit_radius * moon_angle.sin();
-                
-            
-                let moon_model_matrix = create_model_matrix(
-                    Vec3::new(moon_x, moon_y, 0.0),
-                    moon_scale * 10.0,
-                    Vec3::new(0.0, 0.0, 0.0),
-                );
-            
-                let moon_normal_matrix = moon_model_matrix.try_inverse().unwrap().transpose();
-            
-                let moon_uniforms = Uniforms {
-                    normal_matrix: moon_normal_matrix,
-                    model_matrix: moon_model_matrix,
-                    view_matrix: view_matrix,
-                    projection_matrix: Mat4::identity(),
-                    viewport_matrix: Mat4::identity(),
-                    time,
-                    noise_open_simplex: create_open_simplex_noise(),
-                    noise_cellular: create_cellular_noise(),
-                    noise_perlin: create_perlin_noise(),
-                    noise_value: create_value_noise(),
-                    noise_value_cubic: create_value_cubic_noise(),
-                };
-            
-                render(&mut framebuffer, &moon_uniforms, &moon_vertex_array, moon_shader);
-            }
-            
-            
+            framebuffer.set_current_color(0x00FFFF);
+            render(
+                framebuffer,
+                &uniforms,
+                &scene.sphere_vertex_array,
+                "crystal_planet_shader",
+            );
         }
-        
-        // Renderizar el objeto seleccionado con shaders específicos
-        match selected_object {
-            VOLCANIC_PLANET => {
-                let translation = Vec3::new(window_width as f32 / 2.0, window_height as f32 / 2.0, 0.0);
-                let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05);
-                let scale = 30.0;
-                let model_matrix = create_model_matrix(translation, scale, rotation);
+        VORTEX => {
+            let translation = scene.center;
+            let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.1);
+            let scale = 35.0;
+            let model_matrix = create_model_matrix(translation, scale, rotation);
 
-                let uniforms = Uniforms {
-                    model_matrix,
-                    view_matrix: view_matrix,
-                    normal_matrix,
-                    projection_matrix: Mat4::identity(),
-                    viewport_matrix: Mat4::identity(),
-                    time,
-                    noise_open_simplex: create_open_simplex_noise(),
-                    noise_cellular: create_cellular_noise(),
-                    noise_perlin: create_perlin_noise(),
-                    noise_value: create_value_noise(),
-                    noise_value_cubic: create_value_cubic_noise(),
-                };
+            let uniforms = Uniforms {
+                model_matrix,
+                normal_matrix,
+                ..base.clone()
+            };
 
-                framebuffer.set_current_color(0xFF4500);
-                render(
-                    &mut framebuffer,
-                    &uniforms,
-                    &vertex_arrays,
-                    "volcanic_planet_shader",
-                );
-            }
-            CRYSTAL => {
-                let translation = Vec3::new(
-                    window_width as f32 / 2.0,
-                    window_height as f32 / 2.0,
-                    0.0,
-                );
+            framebuffer.set_current_color(0xFF00FF);
+            render(framebuffer, &uniforms, &scene.sphere_vertex_array, "vortex_shader");
+        }
+        RINGED_PLANET => {
+            if let Some(planet) = scene.planets.iter().find(|p| p.name == "Saturn") {
+                let translation = scene.center;
                 let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05);
-                let scale = 30.0;
+                let scale = planet.scale * 10.0;
+    
                 let model_matrix = create_model_matrix(translation, scale, rotation);
-
-                let uniforms = Uniforms {
+    
+                let mut uniforms = Uniforms {
                     model_matrix,
-                    view_matrix: view_matrix,
-                    normal_matrix,
-                    projection_matrix: Mat4::identity(),
-                    viewport_matrix: Mat4::identity(),
-                    time,
-                    noise_open_simplex: create_open_simplex_noise(),
-                    noise_cellular: create_cellular_noise(),
-                    noise_perlin: create_perlin_noise(),
-                    noise_value: create_value_noise(),
-                    noise_value_cubic: create_value_cubic_noise(),
+                    normal_matrix: model_matrix.try_inverse().unwrap().transpose(),
+                    lighting_model: planet.lighting_model,
+                    ..base.clone()
                 };
-
-                framebuffer.set_current_color(0x00FFFF);
+    
+                // Renderizar el planeta
                 render(
-                    &mut framebuffer,
+                    framebuffer,
                     &uniforms,
-                    &vertex_arrays,
-                    "crystal_planet_shader",
-                );
-            }
-            VORTEX => {
-                let translation = Vec3::new(
-                    window_width as f32 / 2.0,
-                    window_height as f32 / 2.0,
-                    0.0,
+                    &scene.sphere_vertex_array,
+                    planet.shader,
                 );
-                let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.1);
-                let scale = 35.0;
-                let model_matrix = create_model_matrix(translation, scale, rotation);
-
-                let uniforms = Uniforms {
-                    model_matrix,
-                    view_matrix: view_matrix,
-                    normal_matrix,
-                    projection_matrix: Mat4::identity(),
-                    viewport_matrix: Mat4::identity(),
-                    time,
-                    noise_open_simplex: create_open_simplex_noise(),
-                    noise_cellular: create_cellular_noise(),
-                    noise_perlin: create_perlin_noise(),
-                    noise_value: create_value_noise(),
-                    noise_value_cubic: create_value_cubic_noise(),
-                };
-
-                framebuffer.set_current_color(0xFF00FF);
-                render(&mut framebuffer, &uniforms, &vertex_arrays, "vortex_shader");
-            }
-            RINGED_PLANET => {
-                if let Some(planet) = planets.iter().find(|p| p.name == "Saturn") {
-                    let translation = Vec3::new(window_width as f32 / 2.0, window_height as f32 / 2.0, 0.0);
-                    let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05);
-                    let scale = planet.scale * 10.0;
-        
-                    let model_matrix = create_model_matrix(translation, scale, rotation);
-        
-                    let mut uniforms = Uniforms {
-                        model_matrix,
-                        view_matrix: view_matrix,
-                        normal_matrix: model_matrix.try_inverse().unwrap().transpose(),
-                        projection_matrix: Mat4::identity(),
-                        viewport_matrix: Mat4::identity(),
-                        time,
-                        noise_open_simplex: create_open_simplex_noise(),
-                        noise_cellular: create_cellular_noise(),
-                        noise_perlin: create_perlin_noise(),
-                        noise_value: create_value_noise(),
-                        noise_value_cubic: create_value_cubic_noise(),
-                    };
-        
-                    // Renderizar el planeta
-                    render(
-                        &mut framebuffer,
-                        &uniforms,
-                        &vertex_arrays,
-                        planet.shader,
+    
+                // Renderizar el anillo si está definido
+                if let (Some(ring_shader), Some(ring_scale)) = (planet.ring_shader, planet.ring_scale) {
+                    let ring_model_matrix = create_model_matrix(
+                        translation,
+                        ring_scale * 10.0,
+                        rotation,
                     );
-        
-                    // Renderizar el anillo si está definido
-                    if let (Some(ring_shader), Some(ring_scale)) = (planet.ring_shader, planet.ring_scale) {
-                        let ring_model_matrix = create_model_matrix(
-                            translation,
-                            ring_scale * 10.0,
-                            rotation,
-                        );
-                        uniforms.model_matrix = ring_model_matrix;
-                        uniforms.normal_matrix = ring_model_matrix.try_inverse().unwrap().transpose();
-        
-                        render(&mut framebuffer, &uniforms, &ring_vertex_array, ring_shader);
-                    }
+                    uniforms.model_matrix = ring_model_matrix;
+                    uniforms.normal_matrix = ring_model_matrix.try_inverse().unwrap().transpose();
+                    // El anillo es un plano de una sola cara: se dibuja por ambos lados
+                    uniforms.cull_mode = CullMode::None;
+    
+                    transparent.push(TransparentDraw::new(uniforms, &scene.ring_vertex_array, ring_shader, BlendMode::Alpha));
                 }
             }
-            ROCKY_PLANET => {
-                let translation = Vec3::new(window_width as f32 / 2.0, window_height as f32 / 2.0, 0.0);
-                let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05);
-                let scale = 25.0;
-
-                let mut uniforms = Uniforms {
-                    model_matrix: create_model_matrix(translation, scale, rotation),
-                    view_matrix: view_matrix,
-                    normal_matrix,
-                    projection_matrix: Mat4::identity(),
-                    viewport_matrix: Mat4::identity(),
-                    time,
-                    noise_open_simplex: create_open_simplex_noise(),
-                    noise_cellular: create_cellular_noise(),
-                    noise_perlin: create_perlin_noise(),
-                    noise_value: create_value_noise(),
-                    noise_value_cubic: create_value_cubic_noise(),
-                };
-
-                framebuffer.set_current_color(0xAAAAAA);
-                render(&mut framebuffer, &uniforms, &vertex_arrays, "rocky_planet");
+        }
+        ROCKY_PLANET => {
+            let translation = scene.center;
+            let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05);
+            let scale = 25.0;
 
-                let moon_orbit_radius = 50.0; 
-                let moon_scale = scale * 0.3; 
+            let mut uniforms = Uniforms {
+                model_matrix: create_model_matrix(translation, scale, rotation),
+                normal_matrix,
+                ..base.clone()
+            };
 
-                let moon_translation = translation
-                    + Vec3::new(
-                        moon_orbit_radius * (time as f32 * 0.05).cos(),
-                        moon_orbit_radius * (time as f32 * 0.05).sin(),
-                        0.0,
-                    );
+            framebuffer.set_current_color(0xAAAAAA);
+            render(framebuffer, &uniforms, &scene.sphere_vertex_array, "rocky_planet");
 
-                let moon_model_matrix = create_model_matrix(moon_translation, moon_scale, rotation);
-                uniforms.model_matrix = moon_model_matrix;
-                render(&mut framebuffer, &uniforms, &vertex_arrays, "moon_shader");
-            }
-            EARTH_LIKE_PLANET => {
-                let translation = Vec3::new(window_width as f32 / 2.0, window_height as f32 / 2.0, 0.0);
-                let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05);
-                let scale = 35.0;
-
-                let uniforms = Uniforms {
-                    model_matrix: create_model_matrix(translation, scale, rotation),
-                    view_matrix: view_matrix,
-                    normal_matrix,
-                    projection_matrix: Mat4::identity(),
-                    viewport_matrix: Mat4::identity(),
-                    time,
-                    noise_open_simplex: create_open_simplex_noise(),
-                    noise_cellular: create_cellular_noise(),
-                    noise_perlin: create_perlin_noise(),
-                    noise_value: create_value_noise(),
-                    noise_value_cubic: create_value_cubic_noise(),
-                };
+            let moon_orbit_radius = 50.0; 
+            let moon_scale = scale * 0.3; 
 
-                framebuffer.set_current_color(0xFFFFFF);
-                render(
-                    &mut framebuffer,
-                    &uniforms,
-                    &vertex_arrays,
-                    "earth_like_planet_shader",
+            let moon_translation = translation
+                + Vec3::new(
+                    moon_orbit_radius * (time as f32 * 0.05).cos(),
+                    moon_orbit_radius * (time as f32 * 0.05).sin(),
+                    0.0,
                 );
-            }
-            _ => {}
+
+            let moon_model_matrix = create_model_matrix(moon_translation, moon_scale, rotation);
+            uniforms.model_matrix = moon_model_matrix;
+            render(framebuffer, &uniforms, &scene.sphere_vertex_array, "moon_shader");
         }
+        EARTH_LIKE_PLANET => {
+            let translation = scene.center;
+            let rotation = Vec3::new(0.0, 0.0, time as f32 * 0.05);
+            let scale = 35.0;
 
-        // Calcular la posición fija de la nave en el centro de la pantalla
-        let jet_translation = Vec3::new(
-            window_width as f32 / 2.0,
-            window_height as f32 / 2.0 + 100.0, 
-            0.0,
-        );
-        let jet_rotation = Vec3::new(0.2, 0.0, 0.0); 
-        let jet_scale = 15.0; 
+            let uniforms = Uniforms {
+                model_matrix: create_model_matrix(translation, scale, rotation),
+                normal_matrix,
+                ..base.clone()
+            };
 
-        let jet_model_matrix = create_model_matrix(jet_translation, jet_scale, jet_rotation);
+            framebuffer.set_current_color(0xFFFFFF);
+            render(
+                framebuffer,
+                &uniforms,
+                &scene.sphere_vertex_array,
+                "earth_like_planet_shader",
+            );
+        }
+        _ => {}
+    }
 
-        // Uniforms para la nave
+    // La nave va fija delante de la cámara, 100 píxeles por debajo del centro de la pantalla.
+    // Su modelo está hecho para la pantalla (y hacia abajo, z hacia adentro), así que se gira
+    // media vuelta en x para llevarlo al espacio de la cámara
+    let jet_distance = 100.0;
+    let pixel_size = camera.pixel_size(jet_distance);
+    let jet_rotation = Vec3::new(0.2, 0.0, 0.0); 
+    let jet_scale = 15.0 * pixel_size; 
+
+    let jet_in_camera = glm::translation(&Vec3::new(0.0, -100.0 * pixel_size, -jet_distance))
+        * glm::rotation(std::f32::consts::PI, &Vec3::x_axis())
+        * create_model_matrix(Vec3::zeros(), jet_scale, jet_rotation);
+    let jet_model_matrix = view_matrix.try_inverse().unwrap() * jet_in_camera;
+
+    // Uniforms para la nave (la malla de la nave no tiene un orden de vértices consistente,
+    // así que no se descartan caras). Cada malla usa los colores de su material.
+    for (mesh, texture) in scene.jet_meshes.iter().zip(&scene.jet_textures) {
         let jet_uniforms = Uniforms {
             model_matrix: jet_model_matrix,
-            view_matrix: Mat4::identity(), 
-            projection_matrix: Mat4::identity(), 
-            viewport_matrix: Mat4::identity(),
             normal_matrix: jet_model_matrix.try_inverse().unwrap().transpose(),
-            time,
-            noise_open_simplex: create_open_simplex_noise(),
-            noise_cellular: create_cellular_noise(),
-            noise_perlin: create_perlin_noise(),
-            noise_value: create_value_noise(),
-            noise_value_cubic: create_value_cubic_noise(),
+            cull_mode: CullMode::None,
+            texture: texture.clone(),
+            material: Some(mesh.material.clone()),
+            ..base.clone()
         };
 
         // Renderizar la nave en el centro de la pantalla
-        render(&mut framebuffer, &jet_uniforms, &jet_vertex_array, "jet_shader");
+        render(framebuffer, &jet_uniforms, &mesh.vertices, "material_shader");
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let headless_options = match HeadlessOptions::from_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+
+    // Sin ventana: renderizar los cuadros directamente a archivos PNG
+    if let Some(options) = headless_options {
+        if let Err(err) = headless::run(&options) {
+            eprintln!("Headless render failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let window_width = 800;
+    let window_height = 600;
+    let framebuffer_width = 800;
+    let framebuffer_height = 600;
+    let frame_delay = Duration::from_millis(16);
+
+    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    framebuffer.set_post_chain(PostChain::standard());
+    let mut window = Window::new(
+        "Solar System",
+        window_width,
+        window_height,
+        WindowOptions::default(),
+    )
+    .unwrap();
+
+    window.set_position(500, 500);
+    window.update();
+
+    //SKYBOX
+    let skybox = Skybox::new(10000); // Ajusta el número de estrellas
+
+    let scene = Scene::load(
+        Vec3::new(window_width as f32 / 2.0, window_height as f32 / 2.0, 0.0),
+        skybox,
+    );
+
+    let mut time = 0;
+
+    // Variable para guardar el cuerpo celeste seleccionado
+    let mut selected_object: u8 = STAR;
+
+    // Definir la cámara al inicio de `main`
+    let mut camera = initial_camera(scene.center, framebuffer_width, framebuffer_height);
+    let mut camera_mode = CameraMode::Free;
+    let mut mouse = MouseState::default();
+
+    let mut stats_timer = Instant::now();
+
+    while window.is_open() {
+        if window.is_key_down(Key::Escape) {
+            break;
+        }
+
+        time += 1;
+
+        handle_focus(&window, &scene, &mut camera, &mut camera_mode);
+
+        handle_mouse(&window, &scene, time, &mut camera, &mut camera_mode, &mut mouse);
+
+        handle_input(&window, &scene, time, &mut camera, &mut camera_mode);
+
+        // Cambiamos el objeto seleccionado con teclas
+        if window.is_key_down(Key::Key1) {
+            selected_object = STAR;
+        } else if window.is_key_down(Key::Key2) {
+            selected_object = VOLCANIC_PLANET;
+        } else if window.is_key_down(Key::Key3) {
+            selected_object = CRYSTAL;
+        } else if window.is_key_down(Key::Key4) {
+            selected_object = VORTEX;
+        } else if window.is_key_down(Key::Key5) {
+            selected_object = RINGED_PLANET;
+        } else if window.is_key_down(Key::Key6) {
+            selected_object = ROCKY_PLANET;
+        } else if window.is_key_down(Key::Key7) {
+            selected_object = EARTH_LIKE_PLANET;
+        }
+
+        // Z: early-z, X: pre-pase de profundidad, M: modo de anti-aliasing
+        if window.is_key_pressed(Key::Z, KeyRepeat::No) {
+            framebuffer.set_early_z(!framebuffer.early_z());
+        }
+        if window.is_key_pressed(Key::X, KeyRepeat::No) {
+            framebuffer.set_depth_prepass(!framebuffer.depth_prepass());
+        }
+        if window.is_key_pressed(Key::M, KeyRepeat::No) {
+            framebuffer.set_anti_aliasing(framebuffer.anti_aliasing().next());
+        }
+
+        // T: operador de mapeo de tonos, - y =: exposición
+        if window.is_key_pressed(Key::T, KeyRepeat::No) {
+            framebuffer.set_tone_mapping(framebuffer.tone_mapping().next());
+        }
+        if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) {
+            framebuffer.set_exposure(framebuffer.exposure() - 0.25);
+        }
+        if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+            framebuffer.set_exposure(framebuffer.exposure() + 0.25);
+        }
+        // B: resplandor (bloom)
+        if window.is_key_pressed(Key::B, KeyRepeat::No) {
+            framebuffer.set_bloom(!framebuffer.bloom());
+        }
+        // V: vista de depuración (wireframe, normales, profundidad, overdraw, objetos)
+        if window.is_key_pressed(Key::V, KeyRepeat::No) {
+            framebuffer.set_debug_view(framebuffer.debug_view().next());
+        }
+        // F1 - F5: activar/desactivar cada efecto de post-proceso, en el orden de la cadena
+        for (index, key) in [Key::F1, Key::F2, Key::F3, Key::F4, Key::F5].into_iter().enumerate() {
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                framebuffer.post_chain_mut().toggle(index);
+            }
+        }
+
+        render_frame(
+            &mut framebuffer,
+            &scene,
+            &camera,
+            time,
+            selected_object,
+            camera_mode.focus(),
+        );
+
+        // Estadísticas en el título de la ventana cada 30 cuadros
+        if time % 30 == 0 {
+            let fps = 30.0 / stats_timer.elapsed().as_secs_f32();
+            stats_timer = Instant::now();
+            let post_effects: Vec<_> = framebuffer.post_chain().enabled_names().collect();
+            let post_effects = if post_effects.is_empty() { "off".to_string() } else { post_effects.join("+") };
+            window.set_title(&format!(
+                "Solar System - {:.0} FPS | overdraw {:.2} | shaded {} | early-z {} | pre-pass {} | AA {} | {} {:+.2} EV | bloom {} | post {} | view {}",
+                fps,
+                framebuffer.overdraw(),
+                framebuffer.stats.shaded,
+                if framebuffer.early_z() { "on" } else { "off" },
+                if framebuffer.depth_prepass() { "on" } else { "off" },
+                framebuffer.anti_aliasing().name(),
+                framebuffer.tone_mapping().name(),
+                framebuffer.exposure(),
+                if framebuffer.bloom() { "on" } else { "off" },
+                post_effects,
+                framebuffer.debug_view().name(),
+            ));
+        }
 
         // Actualizar la ventana una sola vez
         window
@@ -867,58 +1203,70 @@ fn main() {
 }
 
 
-fn handle_input(window: &Window, translation: &mut Vec3, rotation: &mut Vec3, scale: &mut f32) {
-    let move_speed = 40.0; 
-    let rotation_speed = 0.05; 
-    let zoom_speed = 0.05; 
-
-    // Movimiento de cámara
-    if window.is_key_down(Key::Left) {
-        translation.x -= move_speed;
-    }
-    if window.is_key_down(Key::Right) {
-        translation.x += move_speed;
-    }
-    if window.is_key_down(Key::Up) {
-        translation.y += move_speed; 
-    }
-    if window.is_key_down(Key::Down) {
-        translation.y -= move_speed; 
-    }
+// Aceleración por cuadro de la cámara libre al mantener una tecla; la amortiguación de la
+// cámara limita la velocidad a unas 5.7 veces este valor
+const MOVE_ACCELERATION: f32 = 6.0;
+const TURN_ACCELERATION: f32 = 0.008;
+// Duración en cuadros de un warp, y largo de las estelas de las estrellas a media velocidad del
+// warp en radios del cielo (0.0 las desactiva)
+const WARP_FRAMES: u32 = 90;
+const WARP_STREAK_LENGTH: f32 = 0.6;
 
-    // Control de rotación
-    if window.is_key_down(Key::A) {
-        rotation.y += rotation_speed;
-    }
-    if window.is_key_down(Key::D) {
-        rotation.y -= rotation_speed;
-    }
-    if window.is_key_down(Key::W) {
-        rotation.x += rotation_speed;
-    }
-    if window.is_key_down(Key::S) {
-        rotation.x -= rotation_speed;
+fn handle_input(window: &Window, scene: &Scene, time: u32, camera: &mut Camera, mode: &mut CameraMode) {
+    let zoom_speed = 0.05; 
+    let axis = |positive: Key, negative: Key| {
+        (window.is_key_down(positive) as i32 - window.is_key_down(negative) as i32) as f32
+    };
+
+    // Movimiento relativo a hacia dónde mira la cámara: W/S adelante y atrás, A/D a los lados,
+    // R/F arriba y abajo (la cámara mira hacia -z)
+    let thrust = Vec3::new(axis(Key::D, Key::A), axis(Key::R, Key::F), axis(Key::S, Key::W));
+    // Flechas: cabeceo y guiñada; coma y punto: alabeo
+    let turn = Vec3::new(axis(Key::Up, Key::Down), axis(Key::Left, Key::Right), axis(Key::Comma, Key::Period));
+    match mode {
+        CameraMode::Free => camera.fly(thrust * MOVE_ACCELERATION, turn * TURN_ACCELERATION),
+        // Siguiendo a un cuerpo las mismas teclas giran alrededor de él, y W/S acercan o alejan
+        CameraMode::Follow { focus, distance } => {
+            let (position, radius) = focus_body(scene, *focus, time);
+            *distance = follow_dolly(camera, *distance * (1.0 + FOLLOW_DOLLY_SPEED * thrust.z), radius);
+            camera.orbit(position, *distance, turn * TURN_ACCELERATION);
+        }
+        // Durante el warp la cámara va sola hacia el cuerpo, que sigue moviéndose en su órbita
+        CameraMode::Warp { focus, distance, warp } => {
+            let (position, _) = focus_body(scene, *focus, time);
+            let pose = warp.step(&Pose::framing(position, *distance, follow_orientation()));
+            let travel = pose.position - camera.position;
+            camera.set_pose(pose);
+            camera.streak = if travel.norm() > 0.0 {
+                travel.normalize() * warp.speed() * WARP_STREAK_LENGTH
+            } else {
+                Vec3::zeros()
+            };
+            if warp.is_finished() {
+                camera.stop();
+                *mode = CameraMode::Follow { focus: *focus, distance: *distance };
+            }
+        }
     }
 
-    // Zoom
+    // Zoom: Q aleja y E acerca, cambiando el campo de visión
     if window.is_key_down(Key::Q) {
-        *scale *= 1.0 + zoom_speed; 
+        camera.zoom(1.0 + zoom_speed); 
     }
     if window.is_key_down(Key::E) {
-        *scale *= 1.0 - zoom_speed; 
-        if *scale < 0.1 {
-            *scale = 0.1; 
-        }
+        camera.zoom(1.0 - zoom_speed); 
     }
 }
 
-fn handle_warp(
-    window: &Window,
-    warp_points: &[WarpPoint],
-    camera_translation: &mut Vec3,
-    camera_rotation: &mut Vec3,
-    camera_scale: &mut f32,
-) {
+// 1 - 7: viajar hasta un planeta (con Shift, hasta su luna si la tiene) y seguirlo; 0: volver
+// al vuelo libre, también a mitad de un warp
+fn handle_focus(window: &Window, scene: &Scene, camera: &mut Camera, mode: &mut CameraMode) {
+    if window.is_key_pressed(Key::Key0, KeyRepeat::No) {
+        *mode = CameraMode::Free;
+        camera.stop();
+        return;
+    }
+
     let keys = [
         Key::Key1,
         Key::Key2,
@@ -928,20 +1276,174 @@ fn handle_warp(
         Key::Key6,
         Key::Key7,
     ];
+    let moon = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
 
-    for (i, warp_point) in warp_points.iter().enumerate() {
-        if i < keys.len() && window.is_key_down(keys[i]) {
-            // Resetear la cámara a la vista desde arriba
-            *camera_translation = warp_point.position - Vec3::new(400.0, 300.0, 0.0); // Centrar en el planeta
-            *camera_rotation = Vec3::new(0.0, 0.0, 0.0); // Sin rotación
-            *camera_scale = warp_point.zoom_level; // Aplicar el zoom del warp point
+    for (i, planet) in scene.planets.iter().enumerate() {
+        if i < keys.len() && window.is_key_pressed(keys[i], KeyRepeat::No) {
+            let focus = if moon && planet.moon_scale.is_some() { Focus::Moon(i) } else { Focus::Planet(i) };
+            warp_to(scene, camera, mode, focus);
+            return;
+        }
+    }
+}
 
-            println!(
-                "Warping to planet: {}, New Translation: {:?}, New Rotation: {:?}, New Zoom: {}",
-                warp_point.name, camera_translation, camera_rotation, camera_scale
-            );
+// Empieza un warp desde donde está la cámara hasta el cuerpo, para luego seguirlo
+fn warp_to(scene: &Scene, camera: &mut Camera, mode: &mut CameraMode, focus: Focus) {
+    let planet = &scene.planets[focus.planet()];
+    camera.stop();
+    let warp = Warp::new(camera.pose(), WARP_FRAMES);
+    *mode = CameraMode::Warp { focus, distance: follow_distance(camera, planet), warp };
+
+    let part = match focus {
+        Focus::Planet(_) => "",
+        Focus::Moon(_) => " (moon)",
+        Focus::Ring(_) => " (ring)",
+    };
+    println!("Warping to: {}{}", planet.name, part);
+}
 
-            return;
+// Radianes que gira la cámara por píxel arrastrado, píxeles que puede moverse el ratón entre
+// apretar y soltar el botón para que cuente como clic, y zoom por paso de la rueda
+const MOUSE_LOOK_SPEED: f32 = 0.004;
+const CLICK_SLOP: f32 = 3.0;
+const SCROLL_ZOOM: f32 = 0.9;
+
+// Estado del botón izquierdo entre cuadros
+#[derive(Default)]
+struct MouseState {
+    // Dónde se apretó el botón, mientras sigue apretado
+    pressed_at: Option<(f32, f32)>,
+    last_position: Option<(f32, f32)>,
+    // Si se alejó más que CLICK_SLOP desde que se apretó: es un arrastre y no un clic
+    dragging: bool,
+}
+
+// Arrastrar con el botón izquierdo gira la cámara (o la hace girar alrededor del cuerpo que
+// sigue), la rueda hace zoom y un clic sin arrastrar viaja hasta el cuerpo bajo el cursor
+fn handle_mouse(window: &Window, scene: &Scene, time: u32, camera: &mut Camera, mode: &mut CameraMode, mouse: &mut MouseState) {
+    let position = window.get_mouse_pos(MouseMode::Discard);
+    if window.get_mouse_down(MouseButton::Left) {
+        if let (Some((x, y)), Some((pressed_x, pressed_y))) = (position, mouse.pressed_at) {
+            if (x - pressed_x).hypot(y - pressed_y) > CLICK_SLOP {
+                mouse.dragging = true;
+            }
+            if let (true, Some((last_x, last_y))) = (mouse.dragging, mouse.last_position) {
+                // Se arrastra la escena: hacia la derecha la cámara gira a la izquierda
+                if !matches!(mode, CameraMode::Warp { .. }) {
+                    camera.rotate(Vec3::new(y - last_y, x - last_x, 0.0) * MOUSE_LOOK_SPEED);
+                }
+            }
+        } else if mouse.pressed_at.is_none() {
+            mouse.pressed_at = position;
+            mouse.dragging = false;
+        }
+    } else if mouse.pressed_at.take().is_some() && !mouse.dragging {
+        if let Some((x, y)) = position {
+            if let Some(focus) = pick_body(scene, &camera.ray(x, y), time) {
+                warp_to(scene, camera, mode, focus);
+            }
         }
     }
+    mouse.last_position = position;
+
+    if let Some((_, scroll)) = window.get_scroll_wheel() {
+        let factor = SCROLL_ZOOM.powf(scroll);
+        match mode {
+            CameraMode::Follow { focus, distance } => {
+                let (_, radius) = focus_body(scene, *focus, time);
+                *distance = follow_dolly(camera, *distance * factor, radius);
+            }
+            _ => camera.zoom(factor),
+        }
+    }
+}
+
+fn follow_orientation() -> Quat {
+    camera_tilt(FOLLOW_CAMERA_TILT)
+}
+
+// Distancia al cuerpo seguido, sin entrar en él ni perderlo de vista
+fn follow_dolly(camera: &Camera, distance: f32, radius: f32) -> f32 {
+    distance.clamp(radius * FOLLOW_MIN_RADII, camera.far / 2.0)
+}
+
+// Distancia inicial al seguir a un planeta (o a su luna o su anillo): el zoom del planeta es cuántas
+// unidades del mundo ocupa un píxel en el centro de la pantalla
+fn follow_distance(camera: &Camera, planet: &Planet) -> f32 {
+    planet.zoom_level / camera.pixel_size(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cuadro de la animación en el que se revisan las posiciones de los cuerpos
+    const TIME: u32 = 20;
+
+    #[test]
+    fn follow_camera_keeps_the_focused_body_centered() {
+        let (width, height) = (200, 150);
+        let scene = Scene::load(Vec3::new(width as f32 / 2.0, height as f32 / 2.0, 0.0), Skybox::with_seed(10, 7));
+        let mut camera = initial_camera(scene.center, width, height);
+
+        for focus in [Focus::Planet(2), Focus::Moon(2), Focus::Planet(5)] {
+            let distance = 500.0;
+            let mut previous = None;
+            for time in TIME..TIME + 40 {
+                // Girando alrededor del cuerpo mientras este avanza por su órbita
+                let (position, radius) = focus_body(&scene, focus, time);
+                camera.orbit(position, distance, Vec3::new(0.002, 0.01, 0.0));
+                let clip = camera.projection_matrix() * camera.view_matrix() * position.push(1.0);
+                let screen = camera.viewport_matrix() * (clip / clip.w);
+                assert!(
+                    (screen.x - 100.0).abs() < 0.05 && (screen.y - 75.0).abs() < 0.05,
+                    "{:?} off center at time {}: {:?}",
+                    focus,
+                    time,
+                    screen
+                );
+                assert!(((camera.position - position).norm() - distance).abs() < 0.05);
+                assert!(radius > 0.0);
+                if let Some(previous) = previous {
+                    assert!(camera.position != previous);
+                }
+                previous = Some(camera.position);
+            }
+        }
+
+        // La luna es un cuerpo distinto de su planeta, y un planeta sin luna se sigue a sí mismo
+        assert!(focus_body(&scene, Focus::Moon(2), TIME).0 != focus_body(&scene, Focus::Planet(2), TIME).0);
+        assert_eq!(focus_body(&scene, Focus::Moon(0), TIME), focus_body(&scene, Focus::Planet(0), TIME));
+    }
+
+    #[test]
+    fn clicking_picks_the_body_under_the_cursor() {
+        let (width, height) = (200, 150);
+        let scene = Scene::load(Vec3::new(width as f32 / 2.0, height as f32 / 2.0, 0.0), Skybox::with_seed(10, 7));
+        let mut camera = initial_camera(scene.center, width, height);
+        let to_screen = |camera: &Camera, point: Vec3| {
+            let clip = camera.projection_matrix() * camera.view_matrix() * point.push(1.0);
+            (camera.viewport_matrix() * (clip / clip.w)).xy()
+        };
+
+        // Mirando cada cuerpo de costado, para que el planeta, su luna y su anillo no se tapen
+        for focus in [Focus::Planet(2), Focus::Moon(2), Focus::Planet(5), Focus::Moon(5)] {
+            let (position, _) = focus_body(&scene, focus, TIME);
+            camera.frame(position, 3000.0);
+            let screen = to_screen(&camera, position);
+            assert_eq!(pick_body(&scene, &camera.ray(screen.x, screen.y), TIME), Some(focus));
+        }
+
+        // El anillo, entre su borde interior y el exterior, lejos del planeta
+        let (planet, _) = focus_body(&scene, Focus::Planet(5), TIME);
+        let (inner_radius, outer_radius) = scene.ring_radii;
+        let on_ring = planet + Vec3::x() * (inner_radius + outer_radius) / 2.0 * 100.0;
+        camera.frame(planet, 3000.0);
+        let screen = to_screen(&camera, on_ring);
+        assert_eq!(pick_body(&scene, &camera.ray(screen.x, screen.y), TIME), Some(Focus::Ring(5)));
+
+        // Espacio vacío
+        let away = Ray { origin: planet + Vec3::z() * 1000.0, direction: Vec3::z() };
+        assert_eq!(pick_body(&scene, &away, TIME), None);
+    }
 }
@@ -1,9 +1,12 @@
-use tobj;
+use std::path::{Path, PathBuf};
 use nalgebra_glm::{Vec2, Vec3};
+use crate::color::Color;
+use crate::lighting;
 use crate::vertex::Vertex;
 
 pub struct Obj {
     meshes: Vec<Mesh>,
+    materials: Vec<Material>,
 }
 
 struct Mesh {
@@ -11,16 +14,103 @@ struct Mesh {
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
     indices: Vec<u32>,
+    material_id: Option<usize>,
+}
+
+// Material de un archivo .mtl (Ka, Kd, Ks, Ke, Ns y map_Kd)
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub name: String,
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+    pub emissive: Color,
+    pub shininess: f32,
+    // Ruta de la textura difusa, relativa al directorio actual
+    pub diffuse_texture: Option<PathBuf>,
+}
+
+// Valores por defecto de Blender para mallas sin material
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: String::from("default"),
+            ambient: Color::black(),
+            diffuse: Color::new(204, 204, 204),
+            specular: Color::new(128, 128, 128),
+            emissive: Color::black(),
+            shininess: 250.0,
+            diffuse_texture: None,
+        }
+    }
+}
+
+impl Material {
+    fn from_mtl(material: tobj::Material, directory: &Path) -> Self {
+        let defaults = Material::default();
+        let color = |rgb: Option<[f32; 3]>, default: Color| {
+            rgb.map_or(default, |[r, g, b]| {
+                let channel = |c: f32| (c * 255.0).round().clamp(0.0, 255.0) as u8;
+                Color::new(channel(r), channel(g), channel(b))
+            })
+        };
+
+        // tobj no interpreta Ke; llega como parámetro desconocido "r g b"
+        let emissive = material.unknown_param.get("Ke").and_then(|value| {
+            let channels: Vec<f32> = value.split_whitespace().filter_map(|c| c.parse().ok()).collect();
+            match channels[..] {
+                [r, g, b] => Some([r, g, b]),
+                _ => None,
+            }
+        });
+
+        Material {
+            ambient: color(material.ambient, defaults.ambient),
+            diffuse: color(material.diffuse, defaults.diffuse),
+            specular: color(material.specular, defaults.specular),
+            emissive: color(emissive, defaults.emissive),
+            shininess: material.shininess.unwrap_or(defaults.shininess),
+            diffuse_texture: material.diffuse_texture.map(|path| directory.join(path)),
+            name: material.name,
+        }
+    }
+
+    // Parámetros de iluminación del material; el difuso (Kd) es el albedo de los fragmentos.
+    // El .mtl no trae rugosidad: se aproxima a partir del exponente especular (Ns)
+    pub fn lighting(&self) -> lighting::SurfaceMaterial {
+        lighting::SurfaceMaterial {
+            ambient: self.ambient,
+            specular: self.specular,
+            shininess: self.shininess,
+            metallic: 0.0,
+            roughness: (2.0 / (self.shininess + 2.0)).sqrt(),
+            emissive: self.emissive,
+        }
+    }
+}
+
+// Vértices de una malla junto con su material, para dibujarla con "material_shader"
+pub struct MaterialMesh {
+    pub material: Material,
+    pub vertices: Vec<Vertex>,
 }
 
 impl Obj {
     pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
+        let (models, materials) = tobj::load_obj(filename, &tobj::LoadOptions {
             single_index: true,
             triangulate: true,
             ..Default::default()
         })?;
 
+        // Un .mtl ausente o inválido no impide cargar la geometría
+        let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+        let materials = materials
+            .unwrap_or_default()
+            .into_iter()
+            .map(|material| Material::from_mtl(material, directory))
+            .collect();
+
         let meshes = models.into_iter().map(|model| {
             let mesh = model.mesh;
             Mesh {
@@ -34,29 +124,52 @@ impl Obj {
                     .map(|t| Vec2::new(t[0], 1.0 - t[1]))
                     .collect(),
                 indices: mesh.indices,
+                material_id: mesh.material_id,
             }
         }).collect();
 
-        Ok(Obj { meshes })
+        Ok(Obj { meshes, materials })
     }
 
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
-        let mut vertices = Vec::new();
-
-        for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec2::new(0.0, 0.0));
-
-                vertices.push(Vertex::new(position, normal, tex_coords));
+    fn material(&self, mesh: &Mesh) -> Material {
+        mesh.material_id
+            .and_then(|id| self.materials.get(id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // El color de cada vértice es el color difuso del material de su malla
+    fn mesh_vertices(mesh: &Mesh, material: &Material) -> Vec<Vertex> {
+        mesh.indices.iter().map(|&index| {
+            let position = mesh.vertices[index as usize];
+            let normal = mesh.normals.get(index as usize)
+                .cloned()
+                .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+            let tex_coords = mesh.texcoords.get(index as usize)
+                .cloned()
+                .unwrap_or(Vec2::new(0.0, 0.0));
+
+            Vertex {
+                color: material.diffuse,
+                ..Vertex::new(position, normal, tex_coords)
             }
-        }
+        }).collect()
+    }
 
-        vertices
+    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+        self.meshes
+            .iter()
+            .flat_map(|mesh| Obj::mesh_vertices(mesh, &self.material(mesh)))
+            .collect()
     }
-}
\ No newline at end of file
+
+    pub fn get_material_meshes(&self) -> Vec<MaterialMesh> {
+        self.meshes
+            .iter()
+            .map(|mesh| {
+                let material = self.material(mesh);
+                MaterialMesh { vertices: Obj::mesh_vertices(mesh, &material), material }
+            })
+            .collect()
+    }
+}
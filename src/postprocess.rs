@@ -0,0 +1,355 @@
+use crate::color::Color;
+
+// What a post-processing pass works on: the tone-mapped color of every pixel (0.0 to 1.0,
+// before it is quantized for the screen) and its resolved depth (infinite where nothing was
+// drawn). Both are `width` x `height`, row by row.
+pub struct PostFrame<'a> {
+    pub color: &'a mut [Color],
+    pub depth: &'a [f32],
+    pub width: usize,
+    pub height: usize,
+}
+
+impl PostFrame<'_> {
+    // Distance of (x, y) to the center of the frame, 0.0 at the center and 1.0 at the corners,
+    // along with the offset it comes from (in pixels)
+    fn center_offset(&self, x: usize, y: usize) -> (f32, f32, f32) {
+        let (half_width, half_height) = (self.width as f32 * 0.5, self.height as f32 * 0.5);
+        let (dx, dy) = (x as f32 + 0.5 - half_width, y as f32 + 0.5 - half_height);
+        let half_diagonal = (half_width * half_width + half_height * half_height).sqrt();
+        ((dx * dx + dy * dy).sqrt() / half_diagonal, dx, dy)
+    }
+}
+
+// A full-screen pass. Passes keep whatever buffers they need between frames.
+pub trait PostEffect {
+    fn name(&self) -> &'static str;
+    fn apply(&mut self, frame: &mut PostFrame);
+}
+
+struct Pass {
+    effect: Box<dyn PostEffect>,
+    enabled: bool,
+}
+
+// Ordered list of post-processing passes, each of which can be switched on and off
+#[derive(Default)]
+pub struct PostChain {
+    passes: Vec<Pass>,
+}
+
+impl PostChain {
+    pub fn new() -> Self {
+        PostChain::default()
+    }
+
+    // Every effect in the order they are usually applied; only the subtle ones start enabled
+    pub fn standard() -> Self {
+        PostChain::new()
+            .with(ChromaticAberration::default(), false)
+            .with(Sharpen::default(), false)
+            .with(ColorGrading::default(), false)
+            .with(Vignette::default(), true)
+            .with(FilmGrain::default(), true)
+    }
+
+    // Adds a pass at the end of the chain
+    pub fn with(mut self, effect: impl PostEffect + 'static, enabled: bool) -> Self {
+        self.passes.push(Pass { effect: Box::new(effect), enabled });
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.passes.iter().map(|pass| pass.effect.name())
+    }
+
+    pub fn enabled_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.passes.iter().filter(|pass| pass.enabled).map(|pass| pass.effect.name())
+    }
+
+    // True when at least one pass would run
+    pub fn is_active(&self) -> bool {
+        self.passes.iter().any(|pass| pass.enabled)
+    }
+
+    pub fn is_enabled(&self, index: usize) -> bool {
+        self.passes.get(index).is_some_and(|pass| pass.enabled)
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(pass) = self.passes.get_mut(index) {
+            pass.enabled = enabled;
+        }
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        self.set_enabled(index, !self.is_enabled(index));
+    }
+
+    pub fn apply(&mut self, frame: &mut PostFrame) {
+        for pass in self.passes.iter_mut().filter(|pass| pass.enabled) {
+            pass.effect.apply(frame);
+        }
+    }
+}
+
+// Darkens the image towards the corners
+pub struct Vignette {
+    pub strength: f32,
+    // Distance from the center (1.0 = corner) where the darkening starts
+    pub radius: f32,
+    // Distance over which it goes from nothing to `strength`
+    pub softness: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Vignette { strength: 0.45, radius: 0.45, softness: 0.6 }
+    }
+}
+
+impl PostEffect for Vignette {
+    fn name(&self) -> &'static str {
+        "vignette"
+    }
+
+    fn apply(&mut self, frame: &mut PostFrame) {
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let (distance, _, _) = frame.center_offset(x, y);
+                let t = ((distance - self.radius) / self.softness.max(1e-4)).clamp(0.0, 1.0);
+                let darkening = self.strength * t * t * (3.0 - 2.0 * t);
+                let pixel = &mut frame.color[y * frame.width + x];
+                *pixel = *pixel * (1.0 - darkening);
+            }
+        }
+    }
+}
+
+// Animated noise, stronger in the midtones than in the highlights
+pub struct FilmGrain {
+    pub strength: f32,
+    frame: u32,
+}
+
+impl Default for FilmGrain {
+    fn default() -> Self {
+        FilmGrain { strength: 0.035, frame: 0 }
+    }
+}
+
+impl PostEffect for FilmGrain {
+    fn name(&self) -> &'static str {
+        "grain"
+    }
+
+    fn apply(&mut self, frame: &mut PostFrame) {
+        self.frame = self.frame.wrapping_add(1);
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let pixel = &mut frame.color[y * frame.width + x];
+                let response = 1.0 - pixel.luminance().clamp(0.0, 1.0) * 0.5;
+                let grain = noise(x as u32, y as u32, self.frame) * self.strength * response;
+                *pixel = Color::from_f32(pixel.r() + grain, pixel.g() + grain, pixel.b() + grain);
+            }
+        }
+    }
+}
+
+// Integer hash of a pixel and a frame number, mapped to -1.0..=1.0
+fn noise(x: u32, y: u32, frame: u32) -> f32 {
+    let mut hash = x.wrapping_mul(0x8da6_b343) ^ y.wrapping_mul(0xd816_3841) ^ frame.wrapping_mul(0xcb1a_b31f);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2c1b_3c6d);
+    hash ^= hash >> 12;
+    hash = hash.wrapping_mul(0x297a_2d39);
+    hash ^= hash >> 15;
+    hash as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+// Splits red and blue apart towards the edges, like a cheap lens
+pub struct ChromaticAberration {
+    // Offset in pixels of the red and blue channels at the corners
+    pub strength: f32,
+    source: Vec<Color>,
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        ChromaticAberration { strength: 2.5, source: Vec::new() }
+    }
+}
+
+impl PostEffect for ChromaticAberration {
+    fn name(&self) -> &'static str {
+        "aberration"
+    }
+
+    fn apply(&mut self, frame: &mut PostFrame) {
+        self.source.clear();
+        self.source.extend_from_slice(frame.color);
+        let (width, height) = (frame.width, frame.height);
+        let half_diagonal = ((width * width + height * height) as f32).sqrt() * 0.5;
+        let scale = self.strength / half_diagonal;
+
+        for y in 0..height {
+            for x in 0..width {
+                // The offset grows linearly from the center: red outwards, blue inwards
+                let (_, dx, dy) = frame.center_offset(x, y);
+                let (ox, oy) = (dx * scale, dy * scale);
+                let (px, py) = (x as f32, y as f32);
+                let red = sample(&self.source, width, height, px + ox, py + oy, Color::r);
+                let blue = sample(&self.source, width, height, px - ox, py - oy, Color::b);
+                let pixel = &mut frame.color[y * width + x];
+                *pixel = Color::from_f32(red, pixel.g(), blue);
+            }
+        }
+    }
+}
+
+// Bilinear sample of one channel of an image at pixel coordinates, clamped to the edges
+fn sample(image: &[Color], width: usize, height: usize, x: f32, y: f32, channel: fn(&Color) -> f32) -> f32 {
+    let (x, y) = (x.clamp(0.0, (width - 1) as f32), y.clamp(0.0, (height - 1) as f32));
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+    let texel = |x: usize, y: usize| channel(&image[y * width + x]);
+    let top = texel(x0, y0) + (texel(x1, y0) - texel(x0, y0)) * tx;
+    let bottom = texel(x0, y1) + (texel(x1, y1) - texel(x0, y1)) * tx;
+    top + (bottom - top) * ty
+}
+
+// Contrast and saturation adjustments plus a color tint
+pub struct ColorGrading {
+    // 1.0 leaves the image unchanged for all three
+    pub contrast: f32,
+    pub saturation: f32,
+    pub tint: Color,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        ColorGrading { contrast: 1.1, saturation: 1.2, tint: Color::from_f32(1.0, 0.97, 0.92) }
+    }
+}
+
+impl PostEffect for ColorGrading {
+    fn name(&self) -> &'static str {
+        "grading"
+    }
+
+    fn apply(&mut self, frame: &mut PostFrame) {
+        let (contrast, saturation, tint) = (self.contrast, self.saturation, self.tint);
+        for pixel in frame.color.iter_mut() {
+            let gray = pixel.luminance();
+            let grade = |channel: f32, tint: f32| {
+                let saturated = gray + (channel - gray) * saturation;
+                ((saturated - 0.5) * contrast + 0.5) * tint
+            };
+            *pixel = Color::from_f32(grade(pixel.r(), tint.r()), grade(pixel.g(), tint.g()), grade(pixel.b(), tint.b()));
+        }
+    }
+}
+
+// Unsharp mask with the four direct neighbours. Neighbours across a depth edge count as the
+// pixel itself, so silhouettes against the background don't get a dark halo.
+pub struct Sharpen {
+    pub amount: f32,
+    // Relative depth difference treated as an edge
+    pub depth_tolerance: f32,
+    source: Vec<Color>,
+}
+
+impl Default for Sharpen {
+    fn default() -> Self {
+        Sharpen { amount: 0.3, depth_tolerance: 0.05, source: Vec::new() }
+    }
+}
+
+impl PostEffect for Sharpen {
+    fn name(&self) -> &'static str {
+        "sharpen"
+    }
+
+    fn apply(&mut self, frame: &mut PostFrame) {
+        self.source.clear();
+        self.source.extend_from_slice(frame.color);
+        let (width, height, amount) = (frame.width, frame.height, self.amount);
+
+        for y in 0..height {
+            let (up, down) = (y.saturating_sub(1), (y + 1).min(height - 1));
+            for x in 0..width {
+                let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+                let index = y * width + x;
+                let (center, depth) = (self.source[index], frame.depth[index]);
+                let neighbour = |index: usize| {
+                    let other = frame.depth[index];
+                    let same_surface = other == depth || (other - depth).abs() <= self.depth_tolerance * depth.abs().max(1.0);
+                    if same_surface { self.source[index] } else { center }
+                };
+                let neighbours = neighbour(up * width + x)
+                    + neighbour(down * width + x)
+                    + neighbour(y * width + left)
+                    + neighbour(y * width + right);
+                let sharpen = |center: f32, neighbours: f32| center + (4.0 * center - neighbours) * amount;
+                frame.color[index] = Color::from_f32(
+                    sharpen(center.r(), neighbours.r()),
+                    sharpen(center.g(), neighbours.g()),
+                    sharpen(center.b(), neighbours.b()),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::Framebuffer;
+
+    #[test]
+    fn post_processing_chain() {
+        let (width, height) = (32, 32);
+        let gray = Color::from_f32(0.5, 0.5, 0.5);
+
+        // Passes can be toggled by index and report themselves in order
+        let mut chain = PostChain::new().with(Vignette::default(), false).with(Sharpen::default(), true);
+        assert_eq!(chain.names().collect::<Vec<_>>(), ["vignette", "sharpen"]);
+        chain.toggle(0);
+        chain.toggle(1);
+        assert_eq!(chain.enabled_names().collect::<Vec<_>>(), ["vignette"]);
+
+        // The vignette leaves the center alone and darkens the corners
+        let mut color = vec![gray; width * height];
+        let depth = vec![1.0; width * height];
+        chain.apply(&mut PostFrame { color: &mut color, depth: &depth, width, height });
+        assert_eq!(color[16 * width + 16], gray);
+        assert!(color[0].r() < 0.4, "corner {}", color[0]);
+
+        // Sharpening boosts a bright pixel on its surface, but not across a depth edge
+        let mut sharpen = PostChain::new().with(Sharpen::default(), true);
+        let mut color = vec![gray; width * height];
+        let mut depth = vec![1.0; width * height];
+        color[10 * width + 10] = Color::from_f32(0.8, 0.8, 0.8);
+        color[20 * width + 20] = Color::from_f32(0.8, 0.8, 0.8);
+        depth[20 * width + 20] = 0.5;
+        sharpen.apply(&mut PostFrame { color: &mut color, depth: &depth, width, height });
+        assert!(color[10 * width + 10].r() > 0.8 && color[10 * width + 11].r() < 0.5);
+        assert_eq!(color[20 * width + 20], Color::from_f32(0.8, 0.8, 0.8));
+        assert_eq!(color[20 * width + 21], gray);
+
+        // Going through the chain with a neutral pass resolves to the same image as not having one
+        let render = |chain: PostChain| {
+            let mut framebuffer = Framebuffer::new(width, height);
+            for (i, color) in framebuffer.raster_buffers_mut().0.iter_mut().enumerate() {
+                let (x, y) = ((i % width) as f32 / width as f32, (i / width) as f32 / height as f32);
+                *color = Color::from_f32(x, y, 1.0 - x * y);
+            }
+            framebuffer.set_post_chain(chain);
+            framebuffer.resolve();
+            framebuffer.buffer
+        };
+        let neutral = ColorGrading { contrast: 1.0, saturation: 1.0, tint: Color::from_f32(1.0, 1.0, 1.0) };
+        assert_eq!(render(PostChain::new()), render(PostChain::new().with(neutral, true)));
+    }
+}
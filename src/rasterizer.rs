@@ -0,0 +1,256 @@
+use std::sync::Mutex;
+use std::thread;
+use crate::clipping::ClipScratch;
+use crate::color::Color;
+use crate::fragment::fragment_shader;
+use crate::framebuffer::{object_color, AntiAliasing, BlendMode, DebugView, DepthPass, Framebuffer, RenderStats, OVERDRAW_STEP};
+use crate::triangle::{triangle, triangle_bounds, Coverage};
+use crate::vertex::Vertex;
+use crate::Uniforms;
+
+pub const TILE_SIZE: usize = 32;
+
+// Buffers reused by every draw call so a frame does not allocate once they have grown to
+// the size of the largest mesh. `vertices` holds the shaded vertices (plus any created by
+// clipping) and `triangles` the visible triangles as indices into it; `clip` holds the
+// polygons of the triangle being clipped.
+#[derive(Default)]
+pub struct RasterScratch {
+    pub vertices: Vec<Vertex>,
+    pub triangles: Vec<[usize; 3]>,
+    pub clipped: Vec<[usize; 3]>,
+    pub clip: ClipScratch,
+    tiles: Vec<Tile>,
+    size: (usize, usize),
+}
+
+// Framebuffer settings shared by every tile of a draw call
+#[derive(Clone, Copy)]
+struct RasterState {
+    pass: DepthPass,
+    early_z: bool,
+    anti_aliasing: AntiAliasing,
+    blend_mode: BlendMode,
+    depth_write: bool,
+    debug_view: DebugView,
+    object_id: u32,
+}
+
+// A screen tile and the triangles that overlap it. The framebuffer stores its samples tile
+// by tile, so each tile is shaded in place on its own slice and tiles can run in parallel.
+struct Tile {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    triangles: Vec<usize>,
+    stats: RenderStats,
+}
+
+impl Tile {
+    fn new(framebuffer: &Framebuffer, x: usize, y: usize) -> Self {
+        Tile {
+            x,
+            y,
+            width: TILE_SIZE.min(framebuffer.width - x),
+            height: TILE_SIZE.min(framebuffer.height - y),
+            triangles: Vec::new(),
+            stats: RenderStats::default(),
+        }
+    }
+
+    fn region(&self) -> (i32, i32, i32, i32) {
+        (
+            self.x as i32,
+            self.y as i32,
+            (self.x + self.width) as i32 - 1,
+            (self.y + self.height) as i32 - 1,
+        )
+    }
+
+    // Triangles are drawn in submission order, so each sample sees exactly the same
+    // sequence of depth tests as a single-threaded full-screen pass. `buffer` and `zbuffer`
+    // are this tile's samples (see `Framebuffer::sample_index`).
+    fn shade(
+        &mut self,
+        (buffer, zbuffer): (&mut [Color], &mut [f32]),
+        vertices: &[Vertex],
+        triangles: &[[usize; 3]],
+        uniforms: &Uniforms,
+        shader_type: &str,
+        state: RasterState,
+    ) {
+        let RasterState { pass, early_z, anti_aliasing, blend_mode, depth_write, debug_view, object_id } = state;
+        let region = self.region();
+        let samples = anti_aliasing.sample_count();
+        let Tile { x: tile_x, y: tile_y, triangles: bin, stats, .. } = self;
+        // The overdraw view adds one step per shaded fragment to every sample it covers
+        let overdraw = debug_view == DebugView::Overdraw;
+        let count = |buffer: &mut [Color], base: usize, mask: u8| {
+            for s in (0..samples).filter(|s| mask & (1 << s) != 0) {
+                buffer[base + s] = buffer[base + s] + OVERDRAW_STEP;
+            }
+        };
+
+        for &index in bin.iter() {
+            let [i1, i2, i3] = triangles[index];
+            // Debug views replace the fragment shader with a flat color per face or per object
+            let debug_color = match debug_view {
+                DebugView::Normals => {
+                    let normal = (vertices[i1].transformed_normal + vertices[i2].transformed_normal + vertices[i3].transformed_normal)
+                        .normalize();
+                    Some(Color::from_f32(normal.x * 0.5 + 0.5, normal.y * 0.5 + 0.5, normal.z * 0.5 + 0.5))
+                }
+                DebugView::Objects => Some(object_color(object_id)),
+                _ => None,
+            };
+            let shade = |fragment: &_| debug_color.unwrap_or_else(|| fragment_shader(fragment, uniforms, shader_type));
+
+            triangle(&vertices[i1], &vertices[i2], &vertices[i3], region, anti_aliasing, &uniforms.light, |fragment, coverage: &Coverage| {
+                let x = fragment.position.x as usize - *tile_x;
+                let y = fragment.position.y as usize - *tile_y;
+                let base = (y * TILE_SIZE + x) * samples;
+                stats.fragments += 1;
+
+                // Covered samples whose depth test passes (`>=` re-accepts the pre-pass depth)
+                let passing = |inclusive: bool| {
+                    (0..samples).fold(0u8, |mask, s| {
+                        let covered = coverage.mask & (1 << s) != 0;
+                        let stored = zbuffer[base + s];
+                        let depth = coverage.depths[s];
+                        let passes = if inclusive { stored >= depth } else { stored > depth };
+                        if covered && passes { mask | (1 << s) } else { mask }
+                    })
+                };
+
+                match pass {
+                    DepthPass::DepthOnly => {
+                        let mask = passing(false);
+                        for s in (0..samples).filter(|s| mask & (1 << s) != 0) {
+                            zbuffer[base + s] = coverage.depths[s];
+                        }
+                    }
+                    // Depth is already resolved: only the visible fragment reaches the shader
+                    DepthPass::Shading => {
+                        let mask = passing(true);
+                        if mask != 0 && overdraw {
+                            count(buffer, base, mask);
+                            stats.shaded += 1;
+                            stats.written += 1;
+                        } else if mask != 0 {
+                            let color = shade(&fragment);
+                            for s in (0..samples).filter(|s| mask & (1 << s) != 0) {
+                                buffer[base + s] = blend_mode.blend(buffer[base + s], color);
+                            }
+                            stats.shaded += 1;
+                            stats.written += 1;
+                        }
+                    }
+                    DepthPass::Standard => {
+                        let mask = passing(false);
+                        if early_z && mask == 0 {
+                            return;
+                        }
+
+                        stats.shaded += 1;
+                        if overdraw {
+                            count(buffer, base, coverage.mask);
+                        }
+                        let color = if overdraw { Color::black() } else { shade(&fragment) };
+                        if mask != 0 {
+                            for s in (0..samples).filter(|s| mask & (1 << s) != 0) {
+                                if !overdraw {
+                                    buffer[base + s] = blend_mode.blend(buffer[base + s], color);
+                                }
+                                if depth_write {
+                                    zbuffer[base + s] = coverage.depths[s];
+                                }
+                            }
+                            stats.written += 1;
+                        }
+                    }
+                }
+            });
+        }
+    }
+}
+
+// Bins `scratch.triangles` into tiles and rasterizes/shades the tiles across
+// `framebuffer.thread_count()` threads. Fragments are shaded as soon as they are produced.
+pub fn rasterize(framebuffer: &mut Framebuffer, scratch: &mut RasterScratch, uniforms: &Uniforms, shader_type: &str) {
+    if framebuffer.width == 0 || framebuffer.height == 0 {
+        return;
+    }
+
+    let tiles_x = framebuffer.width.div_ceil(TILE_SIZE);
+    let tiles_y = framebuffer.height.div_ceil(TILE_SIZE);
+    let screen = (0, 0, framebuffer.width as i32 - 1, framebuffer.height as i32 - 1);
+
+    // The tile pool is rebuilt only when the framebuffer changes size
+    if scratch.size != (framebuffer.width, framebuffer.height) {
+        scratch.tiles = (0..tiles_x * tiles_y)
+            .map(|index| Tile::new(framebuffer, (index % tiles_x) * TILE_SIZE, (index / tiles_x) * TILE_SIZE))
+            .collect();
+        scratch.size = (framebuffer.width, framebuffer.height);
+    }
+
+    let RasterScratch { vertices, triangles, tiles, .. } = scratch;
+
+    for tile in tiles.iter_mut() {
+        tile.triangles.clear();
+        tile.stats = RenderStats::default();
+    }
+    for (index, &[i1, i2, i3]) in triangles.iter().enumerate() {
+        if let Some((min_x, min_y, max_x, max_y)) = triangle_bounds(&vertices[i1], &vertices[i2], &vertices[i3], screen) {
+            for tile_y in min_y as usize / TILE_SIZE..=max_y as usize / TILE_SIZE {
+                for tile_x in min_x as usize / TILE_SIZE..=max_x as usize / TILE_SIZE {
+                    tiles[tile_y * tiles_x + tile_x].triangles.push(index);
+                }
+            }
+        }
+    }
+
+    let state = RasterState {
+        pass: framebuffer.depth_pass(),
+        early_z: framebuffer.early_z(),
+        anti_aliasing: framebuffer.anti_aliasing(),
+        blend_mode: framebuffer.blend_mode(),
+        depth_write: framebuffer.depth_write(),
+        debug_view: framebuffer.debug_view(),
+        object_id: framebuffer.next_object_id(),
+    };
+    let active = tiles.iter().filter(|tile| !tile.triangles.is_empty()).count();
+    let threads = framebuffer.thread_count().min(active);
+
+    let tile_samples = TILE_SIZE * TILE_SIZE * state.anti_aliasing.sample_count();
+    let (buffer, zbuffer) = framebuffer.raster_buffers_mut();
+    let mut pending = tiles
+        .iter_mut()
+        .zip(buffer.chunks_mut(tile_samples).zip(zbuffer.chunks_mut(tile_samples)))
+        .filter(|(tile, _)| !tile.triangles.is_empty());
+
+    let (vertices, triangles) = (&vertices[..], &triangles[..]);
+    if threads <= 1 {
+        for (tile, target) in pending {
+            tile.shade(target, vertices, triangles, uniforms, shader_type, state);
+        }
+    } else {
+        // Workers pull the next pending tile until none are left
+        let pending = Mutex::new(&mut pending);
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let next = pending.lock().unwrap().next();
+                    match next {
+                        Some((tile, target)) => tile.shade(target, vertices, triangles, uniforms, shader_type, state),
+                        None => break,
+                    }
+                });
+            }
+        });
+    }
+
+    for tile in tiles.iter().filter(|tile| !tile.triangles.is_empty()) {
+        framebuffer.stats += tile.stats;
+    }
+}
@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec3, Vec4};
+use nalgebra_glm::{Mat4, Vec3, Vec4};
 use crate::vertex::Vertex;
 use crate::Uniforms;
 
@@ -11,19 +11,9 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
   );
 
   // Aplicar las matrices de transformación
-  let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
-
-  // Normalizar si 'w' no es 1
-  let w = transformed.w;
-  let transformed_position = if w != 0.0 {
-      Vec3::new(
-          transformed.x / w,
-          transformed.y / w,
-          transformed.z / w
-      )
-  } else {
-      Vec3::new(transformed.x, transformed.y, transformed.z)
-  };
+  let world_position = uniforms.model_matrix * position;
+  let clip_position = uniforms.projection_matrix * uniforms.view_matrix * world_position;
+  let transformed_position = project_to_screen(&clip_position, &uniforms.viewport_matrix);
 
   // Transformar la normal usando normal_matrix
   let transformed_normal = uniforms.normal_matrix * Vec4::new(vertex.normal.x, vertex.normal.y, vertex.normal.z, 0.0);
@@ -40,5 +30,21 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
       color: vertex.color,
       transformed_position,
       transformed_normal,
+      world_position: world_position.xyz(),
+      clip_position,
   }
+}
+
+// División de perspectiva y transformación de viewport
+pub fn project_to_screen(clip_position: &Vec4, viewport_matrix: &Mat4) -> Vec3 {
+  // Normalizar si 'w' no es 1
+  let w = clip_position.w;
+  let ndc = if w != 0.0 {
+      Vec4::new(clip_position.x / w, clip_position.y / w, clip_position.z / w, 1.0)
+  } else {
+      Vec4::new(clip_position.x, clip_position.y, clip_position.z, 1.0)
+  };
+
+  let screen = viewport_matrix * ndc;
+  Vec3::new(screen.x, screen.y, screen.z)
 }
\ No newline at end of file
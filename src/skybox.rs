@@ -1,8 +1,12 @@
 use nalgebra_glm::{Vec3, Vec4};
 use rand::prelude::*;
+use rand::rngs::StdRng;
 use std::f32::consts::PI;
 use crate::{Framebuffer, Uniforms};
 
+// Distance from the camera to every star
+const SKY_RADIUS: f32 = 100.0;
+
 pub struct Star {
     position: Vec3,
     brightness: f32,
@@ -15,14 +19,22 @@ pub struct Skybox {
 
 impl Skybox {
     pub fn new(star_count: usize) -> Self {
-        let mut rng = rand::thread_rng();
+        Self::generate(star_count, &mut rand::thread_rng())
+    }
+
+    // Same star field every time for a given seed (used for reproducible renders)
+    pub fn with_seed(star_count: usize, seed: u64) -> Self {
+        Self::generate(star_count, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn generate<R: Rng>(star_count: usize, rng: &mut R) -> Self {
         let mut stars = Vec::with_capacity(star_count);
 
         for _ in 0..star_count {
             // Generate random spherical coordinates
             let theta = rng.gen::<f32>() * 2.0 * PI;  // Azimuth angle
             let phi = rng.gen::<f32>() * PI;          // Polar angle
-            let radius = 100.0;  // Fixed radius for all stars
+            let radius = SKY_RADIUS;
 
             // Convert spherical to Cartesian coordinates
             let x = radius * phi.sin() * theta.cos();
@@ -43,27 +55,26 @@ impl Skybox {
         Skybox { stars }
     }
 
-    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, camera_position: Vec3) {
+    // `streak` stretches every star into a line along that direction (in sky radii), towards
+    // where it was a moment ago, for the hyperspace effect of a warp; zero draws plain points
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, camera_position: Vec3, streak: Vec3) {
         // let mut rng = rand::thread_rng();
 
         for star in &self.stars {
             // Calculate star position relative to camera
             let position = star.position + camera_position;
+            let Some(screen_pos) = project(uniforms, position) else { continue };
 
-            // Project the star position to screen space
-            let pos_vec4 = Vec4::new(position.x, position.y, position.z, 1.0);
-            let projected = uniforms.projection_matrix * uniforms.view_matrix * pos_vec4;
-
-            // Perform perspective division
-            if projected.w <= 0.0 { continue; }
-            let ndc = projected / projected.w;
-
-            // Apply viewport transform
-            let screen_pos = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
-
-            // Check if star is in front of camera and within screen bounds
-            if screen_pos.z < 0.0 { continue; }
+            if streak != Vec3::zeros() {
+                if let Some(tail) = project(uniforms, position + streak * SKY_RADIUS) {
+                    draw_streak(framebuffer, screen_pos, tail, star.brightness);
+                }
+            }
 
+            // Off the left or top edge (casting would clamp it onto the first row or column)
+            if screen_pos.x < 0.0 || screen_pos.y < 0.0 {
+                continue;
+            }
             let x = screen_pos.x as usize;
             let y = screen_pos.y as usize;
 
@@ -94,9 +105,9 @@ impl Skybox {
                     }
                     3 => {
                         framebuffer.point(x, y, 1000.0);
-                        framebuffer.point(x - 1, y, 1000.0);
+                        framebuffer.point(x.wrapping_sub(1), y, 1000.0);
                         framebuffer.point(x + 1, y, 1000.0);
-                        framebuffer.point(x, y - 1, 1000.0);
+                        framebuffer.point(x, y.wrapping_sub(1), 1000.0);
                         framebuffer.point(x, y + 1, 1000.0);
                     }
                     _ => {}
@@ -104,4 +115,37 @@ impl Skybox {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Screen position of a point, or None if it's behind the camera
+fn project(uniforms: &Uniforms, position: Vec3) -> Option<Vec3> {
+    // Project the star position to screen space
+    let pos_vec4 = Vec4::new(position.x, position.y, position.z, 1.0);
+    let projected = uniforms.projection_matrix * uniforms.view_matrix * pos_vec4;
+
+    // Perform perspective division
+    if projected.w <= 0.0 { return None; }
+    let ndc = projected / projected.w;
+
+    // Apply viewport transform
+    let screen_pos = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+
+    // Check if star is in front of camera
+    if screen_pos.z < 0.0 { return None; }
+    Some(screen_pos.xyz())
+}
+
+// Line from the star back to its tail, fading out towards the tail
+fn draw_streak(framebuffer: &mut Framebuffer, head: Vec3, tail: Vec3, brightness: f32) {
+    let steps = (tail.x - head.x).abs().max((tail.y - head.y).abs()).min(framebuffer.width as f32) as usize;
+    for step in 1..=steps {
+        let t = step as f32 / steps as f32;
+        let (x, y) = (head.x + (tail.x - head.x) * t, head.y + (tail.y - head.y) * t);
+        if x < 0.0 || y < 0.0 {
+            continue;
+        }
+        let intensity = (brightness * (1.0 - t) * 255.0) as u32;
+        framebuffer.set_current_color(intensity << 16 | intensity << 8 | intensity);
+        framebuffer.point(x as usize, y as usize, 1000.0);
+    }
+}
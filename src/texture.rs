@@ -0,0 +1,197 @@
+use std::path::Path;
+use image::{ImageResult, RgbImage};
+use nalgebra_glm::Vec2;
+use crate::color::Color;
+
+// How texture coordinates outside [0, 1] are mapped back onto the image
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl WrapMode {
+    fn apply(self, texel: i64, size: usize) -> usize {
+        let size = size as i64;
+        match self {
+            WrapMode::Repeat => texel.rem_euclid(size) as usize,
+            WrapMode::MirroredRepeat => {
+                let period = texel.rem_euclid(2 * size);
+                if period < size { period as usize } else { (2 * size - 1 - period) as usize }
+            }
+            WrapMode::ClampToEdge => texel.clamp(0, size - 1) as usize,
+        }
+    }
+}
+
+// One level of the mip chain
+struct MipLevel {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+}
+
+impl MipLevel {
+    // Box-filters 2x2 blocks; odd edges reuse their last row/column
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (x0, y0) = ((2 * x).min(self.width - 1), (2 * y).min(self.height - 1));
+                let (x1, y1) = ((2 * x + 1).min(self.width - 1), (2 * y + 1).min(self.height - 1));
+                let block = [
+                    self.texels[y0 * self.width + x0],
+                    self.texels[y0 * self.width + x1],
+                    self.texels[y1 * self.width + x0],
+                    self.texels[y1 * self.width + x1],
+                ];
+                let average = |channel: fn(&Color) -> f32| block.iter().map(channel).sum::<f32>() / 4.0;
+                texels.push(Color::from_f32(average(Color::r), average(Color::g), average(Color::b)));
+            }
+        }
+        MipLevel { width, height, texels }
+    }
+}
+
+pub struct Texture {
+    levels: Vec<MipLevel>,
+    wrap_u: WrapMode,
+    wrap_v: WrapMode,
+}
+
+impl Texture {
+    // Loads any format supported by the `image` crate (PNG, JPEG, ...)
+    pub fn load(path: impl AsRef<Path>) -> ImageResult<Self> {
+        Ok(Texture::from_image(&image::open(path)?.to_rgb8()))
+    }
+
+    // Builds the full mip chain down to 1x1
+    pub fn from_image(image: &RgbImage) -> Self {
+        let mut levels = vec![MipLevel {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            texels: image.pixels().map(|p| Color::new(p[0], p[1], p[2])).collect(),
+        }];
+        if !levels[0].texels.is_empty() {
+            while levels[levels.len() - 1].width > 1 || levels[levels.len() - 1].height > 1 {
+                let next = levels[levels.len() - 1].downsample();
+                levels.push(next);
+            }
+        }
+
+        Texture { levels, wrap_u: WrapMode::Repeat, wrap_v: WrapMode::Repeat }
+    }
+
+    pub fn set_wrap(&mut self, wrap_u: WrapMode, wrap_v: WrapMode) {
+        self.wrap_u = wrap_u;
+        self.wrap_v = wrap_v;
+    }
+
+    fn texel(&self, level: &MipLevel, x: i64, y: i64) -> Color {
+        let x = self.wrap_u.apply(x, level.width);
+        let y = self.wrap_v.apply(y, level.height);
+        level.texels[y * level.width + x]
+    }
+
+    fn sample_level(&self, level: usize, uv: Vec2) -> Color {
+        let level = &self.levels[level];
+
+        // Texel centers sit at half-integer coordinates
+        let x = uv.x * level.width as f32 - 0.5;
+        let y = uv.y * level.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.texel(level, x0, y0).lerp(&self.texel(level, x0 + 1, y0), tx);
+        let bottom = self.texel(level, x0, y0 + 1).lerp(&self.texel(level, x0 + 1, y0 + 1), tx);
+        top.lerp(&bottom, ty)
+    }
+
+    // Bilinear sample of the full-resolution image. v = 0 is the top row of the image; the OBJ
+    // loader already flips the file's v, which starts at the bottom.
+    pub fn sample(&self, uv: Vec2) -> Color {
+        if self.levels[0].texels.is_empty() {
+            return Color::black();
+        }
+        self.sample_level(0, uv)
+    }
+
+    // Trilinear sample: the mip level comes from how many texels one pixel step covers,
+    // given the screen-space UV derivatives of the fragment
+    pub fn sample_grad(&self, uv: Vec2, uv_dx: Vec2, uv_dy: Vec2) -> Color {
+        let lod = self.level_of_detail(uv_dx, uv_dy);
+        if lod <= 0.0 {
+            return self.sample(uv);
+        }
+
+        let last = self.levels.len() - 1;
+        let lower = (lod.floor() as usize).min(last);
+        if lower == last {
+            return self.sample_level(last, uv);
+        }
+        self.sample_level(lower, uv).lerp(&self.sample_level(lower + 1, uv), lod.fract())
+    }
+
+    fn level_of_detail(&self, uv_dx: Vec2, uv_dy: Vec2) -> f32 {
+        let (width, height) = (self.levels[0].width as f32, self.levels[0].height as f32);
+
+        // With repeat wrapping, a jump across the seam (u 0.99 -> 0.01) is a small step
+        let wrap = |d: f32, mode: WrapMode| if mode == WrapMode::Repeat { d - d.round() } else { d };
+        let texels = |d: Vec2| Vec2::new(wrap(d.x, self.wrap_u) * width, wrap(d.y, self.wrap_v) * height).norm();
+
+        let rho = texels(uv_dx).max(texels(uv_dy));
+        if rho > 0.0 { rho.log2() } else { 0.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn texture_wrap_modes() {
+        // Two texels: black on the left, white on the right
+        let image = RgbImage::from_fn(2, 1, |x, _| if x == 0 { Rgb([0, 0, 0]) } else { Rgb([255, 255, 255]) });
+        let sample = |wrap: WrapMode, u: f32| {
+            let mut texture = Texture::from_image(&image);
+            texture.set_wrap(wrap, wrap);
+            (texture.sample(Vec2::new(u, 0.5)).r() * 255.0).round() as u8
+        };
+
+        // Texel centers sample exactly, halfway between them is filtered
+        assert_eq!(sample(WrapMode::Repeat, 0.25), 0);
+        assert_eq!(sample(WrapMode::Repeat, 0.75), 255);
+        assert_eq!(sample(WrapMode::Repeat, 0.5), 128);
+
+        // One texel to the left of the image
+        assert_eq!(sample(WrapMode::Repeat, -0.25), 255);
+        assert_eq!(sample(WrapMode::MirroredRepeat, -0.25), 0);
+        assert_eq!(sample(WrapMode::ClampToEdge, -0.25), 0);
+
+        // Past the right edge, repeat blends back towards the first texel
+        assert_eq!(sample(WrapMode::Repeat, 1.0), 128);
+        assert_eq!(sample(WrapMode::MirroredRepeat, 1.0), 255);
+        assert_eq!(sample(WrapMode::ClampToEdge, 1.0), 255);
+    }
+
+    #[test]
+    fn minified_texture_samples_the_mip_chain() {
+        // One-texel checkerboard: any single texel is black or white, its average is gray
+        let image = RgbImage::from_fn(64, 64, |x, y| if (x + y) % 2 == 0 { Rgb([0, 0, 0]) } else { Rgb([255, 255, 255]) });
+        let texture = Texture::from_image(&image);
+        let uv = Vec2::new(0.3, 0.6);
+
+        let magnified = texture.sample_grad(uv, Vec2::new(0.001, 0.0), Vec2::new(0.0, 0.001));
+        assert_eq!(magnified.r(), texture.sample(uv).r());
+
+        // 16 texels per pixel step: level 4 of the chain
+        let minified = texture.sample_grad(uv, Vec2::new(0.25, 0.0), Vec2::new(0.0, 0.25));
+        assert!((minified.r() * 255.0 - 128.0).abs() <= 2.0, "expected gray, got {:?}", minified);
+    }
+}
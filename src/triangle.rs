@@ -1,9 +1,13 @@
-use nalgebra_glm::{Vec2, Vec3, dot};
+use nalgebra_glm::{Vec2, Vec3};
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
 use crate::line::line;
+use crate::color::Color;
+use crate::framebuffer::AntiAliasing;
+use crate::lighting::Light;
 
-pub fn _triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
+// Solo las aristas del triángulo, para la vista de wireframe
+pub fn wireframe_triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
     let mut fragments = Vec::new();
 
     fragments.extend(line(v1, v2));
@@ -13,50 +17,201 @@ pub fn _triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
     fragments
 }
   
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Back,
+}
+
+// Las caras frontales tienen área negativa según edge_function; las de área nula nunca se dibujan
+pub fn is_culled(v1: &Vertex, v2: &Vertex, v3: &Vertex, cull_mode: CullMode) -> bool {
+    let area = edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position);
+    match cull_mode {
+        CullMode::None => area == 0.0,
+        CullMode::Back => area >= 0.0,
+    }
+}
+
+// Cuadro delimitador del triángulo en píxeles, recortado a `region` (min_x, min_y, max_x, max_y)
+pub fn triangle_bounds(v1: &Vertex, v2: &Vertex, v3: &Vertex, region: (i32, i32, i32, i32)) -> Option<(i32, i32, i32, i32)> {
+    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(
+      &v1.transformed_position,
+      &v2.transformed_position,
+      &v3.transformed_position,
+    );
+
+    let min_x = min_x.max(region.0);
+    let min_y = min_y.max(region.1);
+    let max_x = max_x.min(region.2);
+    let max_y = max_y.min(region.3);
+
+    if min_x > max_x || min_y > max_y {
+      None
+    } else {
+      Some((min_x, min_y, max_x, max_y))
+    }
+}
+
+// Coordenadas baricéntricas de un píxel y sus pesos con corrección de perspectiva
+struct PixelSample {
+    screen: (f32, f32, f32),
+    perspective: (f32, f32, f32),
+    tex_coords: Vec2,
+}
+
+pub const MAX_SAMPLES: usize = 4;
+
+// Muestras del píxel cubiertas por el triángulo (bit i = muestra i) y la profundidad del
+// triángulo en cada una
+#[derive(Clone, Copy)]
+pub struct Coverage {
+    pub mask: u8,
+    pub depths: [f32; MAX_SAMPLES],
+}
+
+// Rasteriza solo los píxeles del triángulo que caen dentro de `region` (inclusive)
+// Los fragmentos se entregan uno por uno a `emit` en lugar de acumularse en un vector, junto
+// con las muestras que cubren. Con sombreado por muestra (SSAA) se emite un fragmento por
+// muestra cubierta; si no, uno por píxel interpolado en el centro. La intensidad de cada
+// fragmento es el término difuso de `light` en su posición del mundo.
+pub fn triangle<F: FnMut(Fragment, &Coverage)>(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    region: (i32, i32, i32, i32),
+    anti_aliasing: AntiAliasing,
+    light: &Light,
+    mut emit: F,
+) {
     let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
-  
-    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
-  
-    let light_dir = Vec3::new(0.0, 0.0, -1.0);
+
+    let Some((min_x, min_y, max_x, max_y)) = triangle_bounds(v1, v2, v3, region) else {
+      return;
+    };
   
     let triangle_area = edge_function(&a, &b, &c);
-  
-    //  Iterar sobre cada píxel en el cuadro delimitador
-    for y in min_y..=max_y {
-      for x in min_x..=max_x {
-        let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
-  
-        // coordenadas
-        let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
-        //verificación del punto
-        if w1 >= 0.0 && w1 <= 1.0 && 
-           w2 >= 0.0 && w2 <= 1.0 &&
-           w3 >= 0.0 && w3 <= 1.0 { 
-            let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-            let normal = normal.normalize();
-            let intensity = dot(&normal, &light_dir).max(0.0);
-
-            let depth = a.z * w1 + b.z * w2 + c.z * w3;
-
-            let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
-
-            let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-            let normal = normal.normalize();
-
-            fragments.push(Fragment::new(
-              Vec2::new(x as f32, y as f32),
-              depth,
-              intensity,
-              vertex_position,
-              normal,
-          ));
+
+    // 1/w de cada vértice para interpolar con corrección de perspectiva
+    let (inv_w1, inv_w2, inv_w3) = (
+      1.0 / v1.clip_position.w,
+      1.0 / v2.clip_position.w,
+      1.0 / v3.clip_position.w,
+    );
+
+    let barycentric = |x: f32, y: f32| {
+        barycentric_coordinates(&Vec3::new(x, y, 0.0), &a, &b, &c, triangle_area)
+    };
+    let inside = |(w1, w2, w3): (f32, f32, f32)| {
+        (0.0..=1.0).contains(&w1) && (0.0..=1.0).contains(&w2) && (0.0..=1.0).contains(&w3)
+    };
+    // La profundidad en pantalla ya es lineal; el resto de atributos se divide por w
+    let depth = |(w1, w2, w3): (f32, f32, f32)| a.z * w1 + b.z * w2 + c.z * w3;
+    let sample = |(w1, w2, w3): (f32, f32, f32)| {
+        let (p1, p2, p3) = (w1 * inv_w1, w2 * inv_w2, w3 * inv_w3);
+        let sum = p1 + p2 + p3;
+        let (p1, p2, p3) = (p1 / sum, p2 / sum, p3 / sum);
+
+        PixelSample {
+            screen: (w1, w2, w3),
+            perspective: (p1, p2, p3),
+            tex_coords: v1.tex_coords * p1 + v2.tex_coords * p2 + v3.tex_coords * p3,
+        }
+    };
+    let fragment = |x: i32, y: i32, pixel: &PixelSample, uv_dx: Vec2, uv_dy: Vec2| {
+        let (p1, p2, p3) = pixel.perspective;
+        let normal = v1.transformed_normal * p1 + v2.transformed_normal * p2 + v3.transformed_normal * p3;
+        let normal = normal.normalize();
+        let vertex_position = v1.position * p1 + v2.position * p2 + v3.position * p3;
+        let world_position = v1.world_position * p1 + v2.world_position * p2 + v3.world_position * p3;
+        let intensity = light.diffuse(&world_position, &normal);
+        let color = interpolate_color(&v1.color, &v2.color, &v3.color, p1, p2, p3);
+
+        Fragment {
+            world_position,
+            uv_dx,
+            uv_dy,
+            ..Fragment::new(
+                Vec2::new(x as f32, y as f32),
+                depth(pixel.screen),
+                intensity,
+                vertex_position,
+                normal,
+                pixel.tex_coords,
+                color,
+            )
+        }
+    };
+
+    let offsets = anti_aliasing.sample_offsets();
+    let multisampled = anti_aliasing != AntiAliasing::Off;
+
+    // Se recorre el cuadro delimitador en bloques de 2x2 píxeles. Los píxeles del bloque fuera
+    // del triángulo se evalúan igual (sin emitirse) para calcular las derivadas de las UV.
+    for quad_y in ((min_y & !1)..=max_y).step_by(2) {
+      for quad_x in ((min_x & !1)..=max_x).step_by(2) {
+        let pixel_at = |i: usize| (quad_x + (i % 2) as i32, quad_y + (i / 2) as i32);
+        let centers = [0, 1, 2, 3].map(|i| {
+            let (x, y) = pixel_at(i);
+            barycentric(x as f32 + 0.5, y as f32 + 0.5)
+        });
+
+        let coverage = [0, 1, 2, 3].map(|i| {
+            let mut coverage = Coverage { mask: 0, depths: [f32::INFINITY; MAX_SAMPLES] };
+            if multisampled {
+                let (x, y) = pixel_at(i);
+                for (s, &(dx, dy)) in offsets.iter().enumerate() {
+                    let weights = barycentric(x as f32 + dx, y as f32 + dy);
+                    if inside(weights) {
+                        coverage.mask |= 1 << s;
+                        coverage.depths[s] = depth(weights);
+                    }
+                }
+            } else if inside(centers[i]) {
+                coverage.mask = 1;
+                coverage.depths[0] = depth(centers[i]);
+            }
+            coverage
+        });
+        if coverage.iter().all(|pixel| pixel.mask == 0) {
+            continue;
+        }
+        let quad = centers.map(sample);
+
+        for (i, pixel) in quad.iter().enumerate() {
+            let (x, y) = pixel_at(i);
+            if coverage[i].mask == 0 || x < min_x || x > max_x || y < min_y || y > max_y {
+                continue;
+            }
+
+            // Diferencias con el vecino de la misma fila y de la misma columna del bloque
+            let (row, column) = (i / 2 * 2, i % 2);
+            let finite_or_zero = |d: Vec2| if d.x.is_finite() && d.y.is_finite() { d } else { Vec2::zeros() };
+            let uv_dx = finite_or_zero(quad[row + 1].tex_coords - quad[row].tex_coords);
+            let uv_dy = finite_or_zero(quad[column + 2].tex_coords - quad[column].tex_coords);
+
+            if anti_aliasing.per_sample_shading() {
+                for (s, &(dx, dy)) in offsets.iter().enumerate() {
+                    if coverage[i].mask & (1 << s) != 0 {
+                        let at_sample = sample(barycentric(x as f32 + dx, y as f32 + dy));
+                        let single = Coverage { mask: 1 << s, ..coverage[i] };
+                        emit(fragment(x, y, &at_sample, uv_dx, uv_dy), &single);
+                    }
+                }
+            } else {
+                emit(fragment(x, y, pixel, uv_dx, uv_dy), &coverage[i]);
+            }
         }
       }
     }
+}
 
-  fragments
+fn interpolate_color(c1: &Color, c2: &Color, c3: &Color, w1: f32, w2: f32, w3: f32) -> Color {
+    let channel = |a: f32, b: f32, c: f32| (a * w1 + b * w2 + c * w3).max(0.0);
+    Color::from_f32(
+        channel(c1.r(), c2.r(), c3.r()),
+        channel(c1.g(), c2.g(), c3.g()),
+        channel(c1.b(), c2.b(), c3.b()),
+    )
 }
 
 fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {